@@ -29,7 +29,7 @@ fn bench_groth(c: &mut Criterion, num_validators: u32, num_constraints: u32) {
         inputs
     };
 
-    let mut wtns = WitnessCalculator::new(format!(
+    let wtns = WitnessCalculator::new(format!(
         "./test-vectors/complex-circuit/complex-circuit-{}-{}.wasm",
         i, j
     ))
@@ -0,0 +1,71 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use ark_circom::WitnessCalculator;
+use num_bigint::BigInt;
+
+fn inputs() -> Vec<(String, Vec<BigInt>)> {
+    vec![
+        ("a".to_string(), vec![BigInt::from(3)]),
+        ("b".to_string(), vec![BigInt::from(11)]),
+    ]
+}
+
+// Compares writing inputs / reading the witness through the shared memory window against
+// one `writeSharedRWMemory` / `readSharedRWMemory` call per 32-bit word.
+fn shared_memory_access(c: &mut Criterion) {
+    let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+
+    c.bench_function("witness multiplier2 direct memory", |b| {
+        b.iter(|| black_box(wtns.calculate_witness(inputs(), false).unwrap()))
+    });
+
+    wtns.set_direct_memory_access(false);
+    c.bench_function("witness multiplier2 per-call memory", |b| {
+        b.iter(|| black_box(wtns.calculate_witness(inputs(), false).unwrap()))
+    });
+}
+
+// Cold start (parse, load, link and the first calculation) and warm calculations on the
+// loaded instance, to compare against upstream circom-compat's wasmer backend: run the same
+// fixture there and compare the reports, e.g. with criterion's `--save-baseline`.
+fn cold_and_warm(c: &mut Criterion) {
+    let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+    let mut group = c.benchmark_group("wasm3 multiplier2");
+
+    group.bench_function("cold start", |b| {
+        b.iter(|| {
+            let wtns = WitnessCalculator::from_bytes(black_box(&data)).unwrap();
+            black_box(wtns.calculate_witness(inputs(), false).unwrap())
+        })
+    });
+
+    let wtns = WitnessCalculator::from_bytes(&data).unwrap();
+    wtns.calculate_witness(inputs(), false).unwrap();
+    group.bench_function("warm", |b| {
+        b.iter(|| black_box(wtns.calculate_witness(inputs(), false).unwrap()))
+    });
+
+    group.finish();
+}
+
+// Compares producing the `.wtns` witness bytes through `BigInt`s against reading the words
+// straight into the byte buffer. The difference grows with the witness size.
+fn raw_bytes(c: &mut Criterion) {
+    let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+
+    c.bench_function("wtns bytes multiplier2 via BigInt", |b| {
+        b.iter(|| {
+            let witness = wtns.calculate_witness(inputs(), false).unwrap();
+            let mut bytes = Vec::new();
+            wtns.write_wtns(&witness, &mut bytes).unwrap();
+            black_box(bytes)
+        })
+    });
+
+    c.bench_function("wtns bytes multiplier2 raw", |b| {
+        b.iter(|| black_box(wtns.calculate_witness_raw_bytes(inputs(), false).unwrap()))
+    });
+}
+
+criterion_group!(benches, shared_memory_access, cold_and_warm, raw_bytes);
+criterion_main!(benches);
@@ -68,7 +68,7 @@ impl<E: Pairing> CircomBuilder<E> {
 
     /// Creates the circuit populated with the witness corresponding to the previously
     /// provided inputs
-    pub fn build(mut self) -> Result<CircomCircuit<E>> {
+    pub fn build(self) -> Result<CircomCircuit<E>> {
         let mut circom = self.setup();
 
         // calculate the witness
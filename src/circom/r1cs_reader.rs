@@ -8,7 +8,7 @@ use ark_ec::pairing::Pairing;
 use ark_serialize::{CanonicalDeserialize, SerializationError, SerializationError::IoError};
 use ark_std::io::{Read, Seek, SeekFrom};
 
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Range};
 
 type IoResult<T> = Result<T, SerializationError>;
 
@@ -38,6 +38,65 @@ impl<E: Pairing> From<R1CSFile<E>> for R1CS<E> {
     }
 }
 
+impl<E: Pairing> R1CS<E> {
+    /// Returns the witness indices of the public signals, i.e. the public outputs followed by
+    /// the public inputs, which come right after the constant one at index 0.
+    ///
+    /// Returns an error if the wire mapping doesn't keep them at those indices, in which case
+    /// the public signals have to be gathered through the mapping.
+    pub fn public_signals_range(&self) -> color_eyre::Result<Range<usize>> {
+        let range = 1..self.num_inputs;
+        if let Some(mapping) = &self.wire_mapping {
+            if let Some(wire) = range.clone().find(|&i| mapping.get(i) != Some(&i)) {
+                return Err(color_eyre::eyre::eyre!(
+                    "Public signal wire {} is not mapped to witness index {}",
+                    wire,
+                    wire
+                ));
+            }
+        }
+        Ok(range)
+    }
+
+    /// Evaluates every constraint `(A·w) * (B·w) = (C·w)` over `witness`, indexed like the
+    /// witness generator's output, reading each wire through the wire mapping if there is
+    /// one. Returns the index of the first unsatisfied constraint, or `None` if the witness
+    /// satisfies all of them.
+    ///
+    /// Returns an error if a constraint refers to a wire the witness doesn't have.
+    #[cfg(feature = "r1cs")]
+    pub fn first_unsatisfied(
+        &self,
+        witness: &[E::ScalarField],
+    ) -> color_eyre::Result<Option<usize>> {
+        let evaluate = |terms: &ConstraintVec<E>| {
+            terms
+                .iter()
+                .try_fold(E::ScalarField::from(0u32), |sum, (wire, coefficient)| {
+                    let index = match &self.wire_mapping {
+                        Some(mapping) => mapping.get(*wire).copied(),
+                        None => Some(*wire),
+                    };
+                    let value = index.and_then(|index| witness.get(index)).ok_or_else(|| {
+                        color_eyre::eyre::eyre!(
+                            "Wire {} is not in the witness of {} elements",
+                            wire,
+                            witness.len()
+                        )
+                    })?;
+                    Ok::<_, color_eyre::Report>(sum + *coefficient * value)
+                })
+        };
+
+        for (i, (a, b, c)) in self.constraints.iter().enumerate() {
+            if evaluate(a)? * evaluate(b)? != evaluate(c)? {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    }
+}
+
 pub struct R1CSFile<E: Pairing> {
     pub version: u32,
     pub header: Header,
@@ -254,6 +313,35 @@ mod tests {
     use ark_bn254::{Bn254, Fr};
     use ark_std::io::{BufReader, Cursor};
 
+    #[test]
+    fn public_signals() {
+        let data = std::fs::read("./test-vectors/multiplier2.r1cs").unwrap();
+        let file = R1CSFile::<Bn254>::new(Cursor::new(data)).unwrap();
+        let r1cs = R1CS::from(file);
+        // the output `c` followed by the public input `a`
+        assert_eq!(r1cs.public_signals_range().unwrap(), 1..3);
+
+        let shuffled = R1CS {
+            wire_mapping: Some(vec![0, 3, 1, 2]),
+            ..r1cs
+        };
+        assert!(shuffled.public_signals_range().is_err());
+    }
+
+    #[cfg(feature = "r1cs")]
+    #[test]
+    fn unsatisfied_constraints() {
+        let data = std::fs::read("./test-vectors/multiplier2.r1cs").unwrap();
+        let r1cs = R1CS::from(R1CSFile::<Bn254>::new(Cursor::new(data)).unwrap());
+
+        let witness = [1, 33, 3, 11].map(Fr::from);
+        assert_eq!(r1cs.first_unsatisfied(&witness).unwrap(), None);
+
+        let wrong = [1, 34, 3, 11].map(Fr::from);
+        assert_eq!(r1cs.first_unsatisfied(&wrong).unwrap(), Some(0));
+        assert!(r1cs.first_unsatisfied(&witness[..2]).is_err());
+    }
+
     #[test]
     fn sample() {
         let data = hex_literal::hex!(
@@ -0,0 +1,64 @@
+//! JavaScript bindings, for generating witnesses from a browser or Node through
+//! `wasm-bindgen`
+use crate::{parse_bigint, WitnessCalculator};
+use color_eyre::Result;
+use wasm_bindgen::prelude::*;
+
+/// Calculates the witness of the circuit whose witness generator wasm is `circuit_bytes`, for
+/// inputs in circom's `input.json` format, and returns it in snarkjs' `.wtns` format. The
+/// inputs are read like [`WitnessCalculator::calculate_witness_from_json`] reads them.
+#[wasm_bindgen(js_name = calculateWitness)]
+pub fn calculate_witness(circuit_bytes: &[u8], inputs_json: &str) -> Result<Vec<u8>, JsValue> {
+    witness_bytes(circuit_bytes, inputs_json).map_err(|e| JsValue::from_str(&format!("{:#}", e)))
+}
+
+/// Like [`calculate_witness`], for a circuit over the field of `prime` (in decimal) whose
+/// elements take `n32` 32-bit words. Knowing the field up front saves instantiating the
+/// circuit just to read it, see [`WitnessCalculator::from_bytes_with_field`].
+#[wasm_bindgen(js_name = calculateWitnessWithField)]
+pub fn calculate_witness_with_field(
+    circuit_bytes: &[u8],
+    inputs_json: &str,
+    prime: &str,
+    n32: u32,
+) -> Result<Vec<u8>, JsValue> {
+    let wtns = parse_bigint(prime)
+        .and_then(|prime| WitnessCalculator::from_bytes_with_field(circuit_bytes, prime, n32));
+    wtns.and_then(|wtns| wtns_bytes(&wtns, inputs_json))
+        .map_err(|e| JsValue::from_str(&format!("{:#}", e)))
+}
+
+fn witness_bytes(circuit_bytes: &[u8], inputs_json: &str) -> Result<Vec<u8>> {
+    let wtns = WitnessCalculator::from_bytes(circuit_bytes)?;
+    wtns_bytes(&wtns, inputs_json)
+}
+
+fn wtns_bytes(wtns: &WitnessCalculator, inputs_json: &str) -> Result<Vec<u8>> {
+    let witness = wtns.calculate_witness_from_json(inputs_json.as_bytes(), false)?;
+    let mut bytes = Vec::new();
+    wtns.write_wtns(&witness, &mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wtns_bytes() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let bytes = witness_bytes(&data, r#"{"a": "3", "b": "11"}"#).unwrap();
+        assert_eq!(&bytes[..4], b"wtns");
+        assert_eq!(
+            witness_bytes(&data, r#"{"a": [3], "b": 11}"#).unwrap(),
+            bytes
+        );
+        assert!(witness_bytes(&data, r#"["a"]"#).is_err());
+        assert!(witness_bytes(&data, r#"{"a": 1.5, "b": 11}"#).is_err());
+
+        let prime = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+        let wtns = WitnessCalculator::from_bytes_with_field(&data, prime.parse().unwrap(), 8);
+        let with_field = wtns_bytes(&wtns.unwrap(), r#"{"a": "3", "b": "11"}"#).unwrap();
+        assert_eq!(with_field, bytes);
+    }
+}
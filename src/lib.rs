@@ -1,14 +1,44 @@
 //! Arkworks - Circom Compatibility layer
 //!
 //! Provides bindings to Circom's R1CS, for Groth16 Proof and Witness generation in Rust.
+//!
+//! The arkworks integration (R1CS, zkey and proving, and the field element witness methods)
+//! is behind the default `arkworks` feature. Without it, witnesses are calculated as
+//! `num-bigint` integers or raw bytes only.
 mod witness;
-pub use witness::WitnessCalculator;
+#[cfg(feature = "call-stats")]
+pub use witness::CallStats;
+#[cfg(feature = "bundle")]
+pub use witness::CircuitBundle;
+#[cfg(feature = "groth16")]
+pub use witness::Groth16Assignment;
+#[cfg(feature = "test-util")]
+pub use witness::MockCircom2;
+pub use witness::{
+    calculate_witness_on, report_exception, Circom2, CircomBase, CircomError, CircomRuntime,
+    CircomVersion, CircuitCost, CircuitRegistry, ErrorLocation, ExitCode, ImportHandler,
+    ImportResolver, InputError, InputValue, KnownCurve, Limits, PartialWitness, PreparedCircuit,
+    WitnessCalculator, WitnessError, WitnessInputs,
+};
+pub use witness::{
+    pack_witness, parse_bigint, read_sym, unpack_witness, witness_from_blob, witness_json,
+    witness_to_blob, write_wtns, write_wtns_file, Symbol, RUNTIME_MODULE,
+};
+#[cfg(feature = "json")]
+pub use witness::{wtns_calculate, InputSource};
 
+#[cfg(feature = "arkworks")]
 pub mod circom;
+#[cfg(feature = "arkworks")]
 pub use circom::{CircomBuilder, CircomCircuit, CircomConfig, CircomReduction};
 
 #[cfg(feature = "ethereum")]
 pub mod ethereum;
 
+#[cfg(feature = "wasm-bindgen")]
+pub mod js;
+
+#[cfg(feature = "arkworks")]
 mod zkey;
+#[cfg(feature = "arkworks")]
 pub use zkey::read_zkey;
@@ -0,0 +1,135 @@
+//! Circuits distributed as a zip archive of their witness generator and symbols
+use super::{sym::label_witness, CircomError, Symbol, WitnessCalculator};
+use color_eyre::{eyre::eyre, Report, Result};
+use num_bigint::BigInt;
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::Path,
+};
+use zip::{result::ZipError, ZipArchive};
+
+/// A circuit loaded from a zip archive holding its witness generator `.wasm`, and optionally
+/// its `.sym` file, anywhere in the archive. Other entries, e.g. input files, are ignored.
+///
+/// The bundled symbols are set as the calculator's symbol table, see
+/// [`WitnessCalculator::witness_for_signal`].
+#[derive(Clone, Debug)]
+pub struct CircuitBundle {
+    calculator: WitnessCalculator,
+}
+
+impl CircuitBundle {
+    /// Opens the zip archive at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path).map_err(CircomError::Io)?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// Reads the zip archive from `reader`.
+    ///
+    /// Returns an error unless the archive contains exactly one `.wasm` entry and at most one
+    /// `.sym` entry.
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self> {
+        let mut archive = ZipArchive::new(reader).map_err(zip_error)?;
+        let wasm = entry(&archive, ".wasm")?
+            .ok_or_else(|| eyre!("The archive doesn't contain a .wasm witness generator"))?;
+        let sym = entry(&archive, ".sym")?;
+
+        let mut data = Vec::new();
+        archive
+            .by_name(&wasm)
+            .map_err(zip_error)?
+            .read_to_end(&mut data)
+            .map_err(CircomError::Io)?;
+        let mut calculator = WitnessCalculator::from_bytes(&data)?;
+
+        if let Some(name) = sym {
+            let file = archive.by_name(&name).map_err(zip_error)?;
+            calculator.set_symbols(super::read_sym(BufReader::new(file))?)?;
+        }
+
+        Ok(Self { calculator })
+    }
+
+    /// Returns the calculator of the bundled circuit.
+    pub fn calculator(&self) -> &WitnessCalculator {
+        &self.calculator
+    }
+
+    /// Returns the calculator of the bundled circuit, e.g. to change its settings.
+    pub fn calculator_mut(&mut self) -> &mut WitnessCalculator {
+        &mut self.calculator
+    }
+
+    /// Returns the symbols of the bundled `.sym` file, if any.
+    pub fn symbols(&self) -> Option<&[Symbol]> {
+        self.calculator.symbols()
+    }
+
+    /// Calculates the witness and labels its elements with the bundled symbols, like
+    /// [`WitnessCalculator::calculate_witness_named`].
+    pub fn calculate_witness_named<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<(String, BigInt)>> {
+        let symbols = self
+            .symbols()
+            .ok_or_else(|| eyre!("The archive doesn't contain a .sym file"))?;
+        let witness = self.calculator.calculate_witness(inputs, sanity_check)?;
+        label_witness(symbols, &witness)
+    }
+}
+
+// Returns the name of the only file entry with the given extension
+fn entry<R: Read + Seek>(archive: &ZipArchive<R>, extension: &str) -> Result<Option<String>> {
+    let mut names = archive
+        .file_names()
+        .filter(|name| name.ends_with(extension) && !name.ends_with('/'));
+    let name = names.next().map(str::to_string);
+    if let Some(other) = names.next() {
+        return Err(eyre!(
+            "The archive contains several {} files: {} and {}",
+            extension,
+            name.unwrap_or_default(),
+            other
+        ));
+    }
+    Ok(name)
+}
+
+fn zip_error(e: ZipError) -> Report {
+    match e {
+        ZipError::Io(e) => CircomError::Io(e).into(),
+        e => CircomError::Load(format!("Invalid zip archive: {}", e)).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_circuit() {
+        let bundle = CircuitBundle::open("./test-vectors/multiplier2.zip").unwrap();
+        assert_eq!(bundle.symbols().unwrap().len(), 3);
+
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(3)]),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ];
+        let named = bundle.calculate_witness_named(inputs, true).unwrap();
+        assert_eq!(named[0], ("main.c".to_string(), BigInt::from(33)));
+        assert_eq!(
+            bundle.calculator().witness_for_signal("main.a").unwrap(),
+            BigInt::from(3)
+        );
+    }
+
+    #[test]
+    fn not_an_archive() {
+        let err = CircuitBundle::open("./test-vectors/multiplier2.wasm").unwrap_err();
+        assert!(matches!(CircomError::of(&err), Some(CircomError::Load(_))));
+    }
+}
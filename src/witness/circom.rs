@@ -1,14 +1,114 @@
-use color_eyre::Result;
-use wasm3::Module;
-pub struct Wasm<'a>(pub Module<'a>);
+use super::CircomError;
+use color_eyre::{eyre::eyre, Result};
+use num_bigint::{BigInt, Sign};
+use std::cell::Cell;
+#[cfg(feature = "call-stats")]
+use std::cell::RefCell;
+#[cfg(feature = "call-stats")]
+use std::collections::BTreeMap;
+use wasm3::{Function, Runtime, WasmArgs, WasmType};
 
+// Functions are looked up through the runtime rather than the module, so that an instance
+// only borrows the runtime owning the loaded module.
+pub struct Wasm<'a> {
+    runtime: &'a Runtime,
+    // offset of the shared read-write memory window in linear memory
+    shared_rw_memory: Option<usize>,
+    // size of the window in 32-bit words, i.e. the circuit's n32, if it could be read
+    n32: Option<u32>,
+    // where the instance is in circom's protocol, if tracked
+    state: Option<&'a Cell<InitState>>,
+    #[cfg(feature = "call-stats")]
+    stats: Option<&'a RefCell<CallStats>>,
+}
+
+// Where an instance is in circom's protocol: every calculation must start with `init`, set
+// the inputs and then read the witness, so that a calculation on a reused instance which
+// skipped `init` is caught instead of computing garbage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum InitState {
+    // `init` never ran on the instance
+    Uninitialized,
+    // `init` ran, and no input was set nor witness read since
+    Fresh,
+    // inputs are being set since the last `init`
+    Writing,
+    // the witness was read, so the next inputs need another `init`
+    Read,
+}
+
+/// Number of calls made to each export of the circuit.
+#[cfg(feature = "call-stats")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallStats {
+    calls: BTreeMap<String, u64>,
+}
+
+#[cfg(feature = "call-stats")]
+impl CallStats {
+    /// Returns the number of calls made to the export `name`.
+    pub fn calls(&self, name: &str) -> u64 {
+        self.calls.get(name).copied().unwrap_or(0)
+    }
+
+    /// Returns the total number of calls made to the circuit's exports.
+    pub fn total(&self) -> u64 {
+        self.calls.values().sum()
+    }
+
+    /// Iterates over the exports which were called and their call counts, by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.calls
+            .iter()
+            .map(|(name, calls)| (name.as_str(), *calls))
+    }
+
+    pub(crate) fn record(&mut self, name: &str) {
+        match self.calls.get_mut(name) {
+            Some(calls) => *calls += 1,
+            None => {
+                self.calls.insert(name.to_string(), 1);
+            }
+        }
+    }
+}
+
+/// The circom compiler version targeted by a witness generator wasm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircomVersion {
+    /// The module has no `getVersion` export, which is assumed to mean circom 1.
+    V1,
+    /// The major version reported by the module's `getVersion` export.
+    V2(u32),
+}
+
+/// Calls into the exports every circom witness generator has.
 pub trait CircomBase {
     fn init(&self, sanity_check: bool) -> Result<()>;
     fn get_u32(&self, name: &str) -> Result<u32>;
-    // Only exists natively in Circom2, hardcoded for Circom
-    fn get_version(&self) -> Result<u32>;
+    // Only exists natively in Circom2, assumed to be Circom if missing
+    fn get_version(&self) -> Result<CircomVersion>;
+
+    /// Reads the null-terminated message circom leaves in its message buffer before calling
+    /// `runtime.printErrorMessage` or `runtime.writeBufferMessage`, one `getMessageChar` call
+    /// per character, without trailing whitespace. Returns an empty string if the buffer is
+    /// empty or the circuit has no message buffer.
+    fn read_message_buffer(&self) -> String {
+        let mut bytes = Vec::new();
+        while bytes.len() < MAX_MESSAGE_LEN {
+            match self.get_u32("getMessageChar") {
+                Ok(0) | Err(_) => break,
+                Ok(c) => bytes.push(c as u8),
+            }
+        }
+        String::from_utf8_lossy(&bytes).trim_end().to_string()
+    }
 }
 
+// Longest message read from the circuit, in case its buffer isn't terminated
+const MAX_MESSAGE_LEN: usize = 4096;
+
+/// Calls into the exports of circom 2 witness generators.
 pub trait Circom2 {
     fn get_field_num_len32(&self) -> Result<u32>;
     fn get_raw_prime(&self) -> Result<()>;
@@ -17,6 +117,82 @@ pub trait Circom2 {
     fn set_input_signal(&self, hmsb: u32, hlsb: u32, pos: u32) -> Result<()>;
     fn get_witness(&self, i: u32) -> Result<()>;
     fn get_witness_size(&self) -> Result<u32>;
+    // `None` if the circuit doesn't export `getInputSize`
+    fn get_input_size(&self) -> Result<Option<u32>>;
+    // `None` if the circuit doesn't export `getInputSignalSize`, 0 for unknown signals
+    fn get_input_signal_size(&self, hmsb: u32, hlsb: u32) -> Result<Option<u32>>;
+
+    /// Writes `values` to the start of the shared read-write memory, least significant word
+    /// first.
+    fn write_shared_rw_memory_bulk(&self, values: &[u32]) -> Result<()> {
+        for (i, v) in values.iter().enumerate() {
+            self.write_shared_rw_memory(i as u32, *v)?;
+        }
+        Ok(())
+    }
+
+    /// Fills `out` from the start of the shared read-write memory, least significant word
+    /// first.
+    fn read_shared_rw_memory_bulk(&self, out: &mut [u32]) -> Result<()> {
+        for (i, v) in out.iter_mut().enumerate() {
+            *v = self.read_shared_rw_memory(i as u32)?;
+        }
+        Ok(())
+    }
+}
+
+/// A loaded circom 2 witness generator, whatever the wasm engine running it.
+///
+/// The crate runs circuits with wasm3, but implementing [`CircomBase`] and [`Circom2`] for
+/// an instance of another engine (wasmer, wasmtime, ...) makes it a `CircomRuntime`, which
+/// [`calculate_witness_on`](crate::calculate_witness_on) calculates witnesses with. The
+/// engine's `runtime.exceptionHandler` import must forward the code to
+/// [`report_exception`](crate::report_exception).
+pub trait CircomRuntime: CircomBase + Circom2 {
+    /// Reads the prime of the circuit's field, which doesn't require `init`.
+    fn prime(&self) -> Result<BigInt> {
+        let n32 = self.get_field_num_len32()?;
+        self.get_raw_prime()?;
+        from_array32(read_limbs_be(self, n32)?)
+    }
+}
+
+impl<T: CircomBase + Circom2> CircomRuntime for T {}
+
+// Largest field element accepted from a circuit, in 32-bit words. circom's primes are at most
+// 256 bits, so this leaves plenty of room while bounding what a corrupt `getFieldNumLen32` can
+// make us allocate.
+const MAX_N32: u32 = 64;
+
+// Checks the number of 32-bit words of a field element reported by the circuit
+pub(crate) fn check_n32(n32: u32) -> Result<u32> {
+    if n32 == 0 || n32 > MAX_N32 {
+        return Err(eyre!(
+            "Field elements of {} words are not supported (at most {})",
+            n32,
+            MAX_N32
+        ));
+    }
+    Ok(n32)
+}
+
+// Reads the field element in the shared read-write memory as its `n32` 32-bit limbs, most
+// significant first. The circuit stores them least significant first.
+pub(crate) fn read_limbs_be<C: Circom2 + ?Sized>(instance: &C, n32: u32) -> Result<Vec<u32>> {
+    let mut limbs = vec![0; check_n32(n32)? as usize];
+    instance.read_shared_rw_memory_bulk(&mut limbs)?;
+    limbs.reverse();
+    Ok(limbs)
+}
+
+// Converts the limbs of a field element, most significant first. The number of limbs comes
+// from the circuit, so it is bounded like `n32` rather than building an arbitrarily large
+// number out of a corrupt read.
+pub(crate) fn from_array32(mut arr: Vec<u32>) -> Result<BigInt> {
+    check_n32(arr.len() as u32)?;
+    // built from the limbs in place rather than through a temporary per limb
+    arr.reverse();
+    Ok(BigInt::from_slice(Sign::Plus, &arr))
 }
 
 impl<'a> Circom2 for Wasm<'a> {
@@ -25,84 +201,251 @@ impl<'a> Circom2 for Wasm<'a> {
     }
 
     fn get_raw_prime(&self) -> Result<()> {
-        let func = self
-            .0
-            .find_function::<(), ()>("getRawPrime")
-            .expect("Unable to find function");
-        func.call().unwrap();
-        Ok(())
+        let func = self.function::<(), ()>("getRawPrime")?;
+        func.call().map_err(|e| trapped("getRawPrime", e))
     }
 
     fn read_shared_rw_memory(&self, i: u32) -> Result<u32> {
-        let func = self
-            .0
-            .find_function::<i32, i32>("readSharedRWMemory")
-            .expect("Unable to find function");
-        let result = func.call(i as i32).unwrap();
+        self.check_shared_rw_memory(i as usize + 1)?;
+        let func = self.function::<i32, i32>("readSharedRWMemory")?;
+        let result = func
+            .call(i as i32)
+            .map_err(|e| trapped("readSharedRWMemory", e))?;
         Ok(result as u32)
     }
 
     fn write_shared_rw_memory(&self, i: u32, v: u32) -> Result<()> {
-        let func = self
-            .0
-            .find_function::<(i32, i32), ()>("writeSharedRWMemory")
-            .expect("Unable to find function");
-        func.call(i as i32, v as i32).unwrap();
-        Ok(())
+        self.check_shared_rw_memory(i as usize + 1)?;
+        let func = self.function::<(i32, i32), ()>("writeSharedRWMemory")?;
+        func.call(i as i32, v as i32)
+            .map_err(|e| trapped("writeSharedRWMemory", e))
     }
 
     fn set_input_signal(&self, hmsb: u32, hlsb: u32, pos: u32) -> Result<()> {
-        let func = self
-            .0
-            .find_function::<(i32, i32, i32), ()>("setInputSignal")
-            .expect("Unable to find function");
-        let _ = func.call(hmsb as i32, hlsb as i32, pos as i32);
-        Ok(())
+        if let Some(state) = self.state {
+            match state.get() {
+                InitState::Uninitialized | InitState::Read => {
+                    return Err(eyre!(
+                        "Attempted to set an input of an instance which wasn't initialized \
+                         since its last calculation"
+                    ))
+                }
+                InitState::Fresh | InitState::Writing => state.set(InitState::Writing),
+            }
+        }
+        let func = self.function::<(i32, i32, i32), ()>("setInputSignal")?;
+        func.call(hmsb as i32, hlsb as i32, pos as i32)
+            .map_err(|e| trapped("setInputSignal", e))
     }
 
     fn get_witness(&self, i: u32) -> Result<()> {
-        let func = self
-            .0
-            .find_function::<i32, ()>("getWitness")
-            .expect("Unable to find function");
-        func.call(i as i32).unwrap();
-        Ok(())
+        // reading the witness of the last calculation again is fine, e.g. element by element
+        if let Some(state) = self.state {
+            if state.get() == InitState::Uninitialized {
+                return Err(eyre!(
+                    "Attempted to read the witness of an instance which wasn't initialized"
+                ));
+            }
+            state.set(InitState::Read);
+        }
+        let func = self.function::<i32, ()>("getWitness")?;
+        func.call(i as i32).map_err(|e| trapped("getWitness", e))
     }
 
     fn get_witness_size(&self) -> Result<u32> {
         self.get_u32("getWitnessSize")
     }
+
+    fn get_input_size(&self) -> Result<Option<u32>> {
+        match self.optional_function::<(), i32>("getInputSize") {
+            Some(func) => func
+                .call()
+                .map(|size| Some(size as u32))
+                .map_err(|e| trapped("getInputSize", e)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_input_signal_size(&self, hmsb: u32, hlsb: u32) -> Result<Option<u32>> {
+        match self.optional_function::<(i32, i32), i32>("getInputSignalSize") {
+            Some(func) => func
+                .call(hmsb as i32, hlsb as i32)
+                .map(|size| Some(size as u32))
+                .map_err(|e| trapped("getInputSignalSize", e)),
+            None => Ok(None),
+        }
+    }
+
+    fn write_shared_rw_memory_bulk(&self, values: &[u32]) -> Result<()> {
+        self.check_shared_rw_memory(values.len())?;
+        let written = self.with_shared_rw_memory(values.len(), |memory| {
+            for (chunk, v) in memory.chunks_exact_mut(4).zip(values) {
+                chunk.copy_from_slice(&v.to_le_bytes());
+            }
+        });
+        if written.is_none() {
+            for (i, v) in values.iter().enumerate() {
+                self.write_shared_rw_memory(i as u32, *v)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_shared_rw_memory_bulk(&self, out: &mut [u32]) -> Result<()> {
+        self.check_shared_rw_memory(out.len())?;
+        let read = self.with_shared_rw_memory(out.len(), |memory| {
+            for (chunk, v) in memory.chunks_exact(4).zip(out.iter_mut()) {
+                *v = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            }
+        });
+        if read.is_none() {
+            for (i, v) in out.iter_mut().enumerate() {
+                *v = self.read_shared_rw_memory(i as u32)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<'a> CircomBase for Wasm<'a> {
     fn init(&self, sanity_check: bool) -> Result<()> {
-        let func = self
-            .0
-            .find_function::<i32, ()>("init")
-            .expect("Unable to find function");
-        func.call(sanity_check as i32).unwrap();
+        let func = self.function::<i32, ()>("init")?;
+        func.call(sanity_check as i32)
+            .map_err(|e| trapped("init", e))?;
+        if let Some(state) = self.state {
+            state.set(InitState::Fresh);
+        }
         Ok(())
     }
 
-    fn get_version(&self) -> Result<u32> {
-        match self.0.find_function::<(), i32>("getVersion") {
-            Ok(func) => Ok(func.call().unwrap() as u32),
-            Err(_) => Ok(1),
+    fn get_version(&self) -> Result<CircomVersion> {
+        match self.optional_function::<(), i32>("getVersion") {
+            Some(func) => func
+                .call()
+                .map(|version| CircomVersion::V2(version as u32))
+                .map_err(|e| trapped("getVersion", e)),
+            None => Ok(CircomVersion::V1),
         }
     }
 
     fn get_u32(&self, name: &str) -> Result<u32> {
-        let func = self
-            .0
-            .find_function::<(), i32>(name)
-            .expect("Unable to find function");
-        let result = func.call().unwrap();
+        let func = self.function::<(), i32>(name)?;
+        let result = func.call().map_err(|e| trapped(name, e))?;
         Ok(result as u32)
     }
 }
 
 impl<'a> Wasm<'a> {
-    pub fn new(instance: Module<'a>) -> Wasm<'a> {
-        Self(instance)
+    pub fn new(runtime: &'a Runtime) -> Wasm<'a> {
+        // circom 2 exposes where `readSharedRWMemory` / `writeSharedRWMemory` operate, which
+        // lets us access the window directly instead of paying a call per 32-bit word
+        let shared_rw_memory = runtime
+            .find_function::<(), i32>("getSharedRWMemoryStart")
+            .ok()
+            .and_then(|func| func.call().ok())
+            .map(|start| start as u32 as usize);
+        let n32 = runtime
+            .find_function::<(), i32>("getFieldNumLen32")
+            .ok()
+            .and_then(|func| func.call().ok())
+            .map(|n32| n32 as u32);
+        Self {
+            runtime,
+            shared_rw_memory,
+            n32,
+            state: None,
+            #[cfg(feature = "call-stats")]
+            stats: None,
+        }
+    }
+
+    /// Tracks the instance's [`InitState`] in `state`, which is shared by every `Wasm` of the
+    /// same instance, making reading the witness fail until `init` ran, and setting inputs
+    /// fail once the witness was read until `init` runs again.
+    pub(crate) fn tracking_init(mut self, state: &'a Cell<InitState>) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    // Whether `init` ran on the tracked instance and nothing used it since, so that a
+    // calculation can skip `init`
+    pub(crate) fn is_fresh(&self) -> bool {
+        self.state
+            .map_or(false, |state| state.get() == InitState::Fresh)
+    }
+
+    // Looks up an export to call it, naming it in the error if the circuit doesn't have it
+    fn function<A: WasmArgs, R: WasmType>(&self, name: &str) -> Result<Function<'a, A, R>> {
+        let func = self
+            .runtime
+            .find_function::<A, R>(name)
+            .map_err(|_| CircomError::MissingExport(name.to_string()))?;
+        self.record_call(name);
+        Ok(func)
+    }
+
+    // Looks up an export to call it, if the circuit has it
+    fn optional_function<A: WasmArgs, R: WasmType>(
+        &self,
+        name: &str,
+    ) -> Option<Function<'a, A, R>> {
+        let func = self.runtime.find_function::<A, R>(name).ok()?;
+        self.record_call(name);
+        Some(func)
+    }
+
+    #[cfg(feature = "call-stats")]
+    fn record_call(&self, name: &str) {
+        if let Some(stats) = self.stats {
+            stats.borrow_mut().record(name);
+        }
+    }
+
+    #[cfg(not(feature = "call-stats"))]
+    fn record_call(&self, _name: &str) {}
+
+    /// Counts the calls made to every export in `stats`.
+    #[cfg(feature = "call-stats")]
+    pub fn counting_calls(mut self, stats: &'a RefCell<CallStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Goes through the `readSharedRWMemory` / `writeSharedRWMemory` exports for every word
+    /// instead of accessing the shared memory window directly.
+    pub fn without_direct_memory(mut self) -> Self {
+        self.shared_rw_memory = None;
+        self
+    }
+
+    // The protocol only defines the first n32 words of the shared memory, so accessing words
+    // past them is a bug of the caller which would otherwise silently corrupt field elements
+    fn check_shared_rw_memory(&self, words: usize) -> Result<()> {
+        match self.n32 {
+            Some(n32) if words > n32 as usize => Err(eyre!(
+                "Shared memory access of {} words is out of bounds for field elements of {} words",
+                words,
+                n32
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs `f` over the first `words` 32-bit words of the shared read-write memory, or
+    /// returns `None` if the window can't be accessed directly.
+    fn with_shared_rw_memory<T>(&self, words: usize, f: impl FnOnce(&mut [u8]) -> T) -> Option<T> {
+        let start = self.shared_rw_memory?;
+        // SAFETY: no wasm function is executing while the slice is alive, and it is fetched
+        // anew on every access since calls into the module may grow (and move) the memory.
+        let memory = unsafe { &mut *self.runtime.memory_mut() };
+        memory.get_mut(start..start + words * 4).map(f)
+    }
+}
+
+fn trapped(name: &str, e: wasm3::error::Error) -> color_eyre::Report {
+    CircomError::Trap {
+        function: name.to_string(),
+        reason: e.to_string(),
     }
+    .into()
 }
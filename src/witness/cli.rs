@@ -0,0 +1,180 @@
+//! Plumbing for command line witness generators, following `snarkjs wtns calculate`
+use super::{CircomError, WitnessCalculator};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use num_bigint::BigInt;
+use std::{env::VarError, fs::File, io::BufReader, path::PathBuf};
+
+/// Where a command line tool reads the circuit's `input.json` from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputSource {
+    /// The file at the path.
+    File(PathBuf),
+    /// The standard input, given as `-` on the command line.
+    Stdin,
+    /// The value of the environment variable, given as `env:NAME` on the command line.
+    Env(String),
+}
+
+impl InputSource {
+    /// Parses a command line argument: `-` for the standard input, `env:NAME` for the
+    /// environment variable `NAME`, and the path of a file otherwise.
+    pub fn from_arg(arg: &str) -> Self {
+        match arg {
+            "-" => InputSource::Stdin,
+            _ => match arg.strip_prefix("env:") {
+                Some(name) => InputSource::Env(name.to_string()),
+                None => InputSource::File(PathBuf::from(arg)),
+            },
+        }
+    }
+
+    /// Reads the inputs from the source and calculates the witness with them, see
+    /// [`WitnessCalculator::calculate_witness_from_json`].
+    pub fn calculate_witness(
+        &self,
+        wtns: &WitnessCalculator,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        self.calculate_witness_with_env(wtns, sanity_check, |name| std::env::var(name))
+    }
+
+    // Like `calculate_witness`, reading environment variables with `env`
+    fn calculate_witness_with_env(
+        &self,
+        wtns: &WitnessCalculator,
+        sanity_check: bool,
+        env: impl Fn(&str) -> Result<String, VarError>,
+    ) -> Result<Vec<BigInt>> {
+        match self {
+            InputSource::File(path) => {
+                let file = File::open(path)
+                    .map_err(CircomError::Io)
+                    .wrap_err_with(|| format!("Unable to open {}", path.display()))?;
+                wtns.calculate_witness_from_json(BufReader::new(file), sanity_check)
+            }
+            InputSource::Stdin => {
+                wtns.calculate_witness_from_json(std::io::stdin().lock(), sanity_check)
+            }
+            InputSource::Env(name) => {
+                let json = env(name)
+                    .map_err(|e| eyre!("Unable to read environment variable {}: {}", name, e))?;
+                wtns.calculate_witness_from_json(json.as_bytes(), sanity_check)
+            }
+        }
+    }
+}
+
+const USAGE: &str =
+    "Usage: <circuit.wasm> <input.json | - | env:NAME> <witness.wtns> [--sanity-check]";
+
+/// Runs the witness step of a command line tool like `snarkjs wtns calculate`, with `args`
+/// (without the program name) being `<circuit.wasm> <input> <witness.wtns>`, optionally
+/// followed by `--sanity-check`. The input is parsed with [`InputSource::from_arg`], so it
+/// can be piped in with `-`, and the witness is written as a `.wtns` file.
+///
+/// ```no_run
+/// fn main() -> color_eyre::Result<()> {
+///     ark_circom::wtns_calculate(std::env::args().skip(1))
+/// }
+/// ```
+pub fn wtns_calculate<I: IntoIterator<Item = String>>(args: I) -> Result<()> {
+    wtns_calculate_with_env(args, |name| std::env::var(name))
+}
+
+// Like `wtns_calculate`, reading environment variables with `env`
+fn wtns_calculate_with_env<I: IntoIterator<Item = String>>(
+    args: I,
+    env: impl Fn(&str) -> Result<String, VarError>,
+) -> Result<()> {
+    let mut paths = Vec::new();
+    let mut sanity_check = false;
+    for arg in args {
+        match arg.as_str() {
+            "--sanity-check" => sanity_check = true,
+            flag if flag.starts_with("--") => {
+                return Err(eyre!("Unknown option {}\n{}", flag, USAGE));
+            }
+            _ => paths.push(arg),
+        }
+    }
+    let (circuit, input, output) = match paths.as_slice() {
+        [circuit, input, output] => (circuit, input, output),
+        _ => {
+            return Err(eyre!(
+                "Expected 3 arguments, got {}\n{}",
+                paths.len(),
+                USAGE
+            ))
+        }
+    };
+
+    let wtns = WitnessCalculator::from_file(circuit)?;
+    let witness =
+        InputSource::from_arg(input).calculate_witness_with_env(&wtns, sanity_check, env)?;
+    wtns.write_wtns_file(&witness, output)
+        .wrap_err_with(|| format!("Unable to write {}", output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_sources() {
+        assert_eq!(InputSource::from_arg("-"), InputSource::Stdin);
+        assert_eq!(
+            InputSource::from_arg("env:INPUT"),
+            InputSource::Env("INPUT".to_string())
+        );
+        assert_eq!(
+            InputSource::from_arg("input.json"),
+            InputSource::File(PathBuf::from("input.json"))
+        );
+    }
+
+    #[test]
+    fn calculates_wtns() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(3)]),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ];
+        let mut expected = Vec::new();
+        let witness = wtns.calculate_witness(inputs, false).unwrap();
+        wtns.write_wtns(&witness, &mut expected).unwrap();
+
+        // unique to the test process, so that concurrent runs don't share the file
+        let path =
+            std::env::temp_dir().join(format!("ark-circom-cli-test-{}.wtns", std::process::id()));
+        let output = path.to_str().unwrap().to_string();
+
+        let args = |input: &str| {
+            vec![
+                "./test-vectors/multiplier2.wasm".to_string(),
+                input.to_string(),
+                output.clone(),
+            ]
+        };
+        wtns_calculate(args("./test-vectors/input.json")).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+
+        // an environment of its own rather than the process', which other tests share
+        let env = |name: &str| match name {
+            "INPUT" => Ok(r#"{"a": 3, "b": 11}"#.to_string()),
+            _ => Err(VarError::NotPresent),
+        };
+        let mut env_args = args("env:INPUT");
+        env_args.push("--sanity-check".to_string());
+        wtns_calculate_with_env(env_args, env).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+        std::fs::remove_file(path).unwrap();
+
+        let err = wtns_calculate_with_env(args("env:UNSET"), env).unwrap_err();
+        assert!(err.to_string().contains("UNSET"));
+        let err = wtns_calculate(vec!["circuit.wasm".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("Usage"));
+    }
+}
@@ -0,0 +1,120 @@
+//! Well-known fields circuits are compiled for, to tell which curve a circuit's prime belongs to
+use num_bigint::BigInt;
+use std::{convert::TryInto, fmt};
+
+/// A curve (or field) whose prime circuits are commonly compiled with, following the names of
+/// circom's `--prime` option. For pairing-friendly curves, the prime is the order of the
+/// scalar field, which is the field circuits are written over. For the Pasta cycle, circom
+/// names the curves by their base field, so the prime of [`KnownCurve::Pallas`] is the base
+/// field of Pallas, i.e. the scalar field of Vesta, and the other way around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KnownCurve {
+    /// BN254 (also known as alt_bn128 or bn128), circom's default.
+    Bn254,
+    /// BLS12-381.
+    Bls12_381,
+    /// BLS12-377.
+    Bls12_377,
+    /// The base field of Pallas, which is the scalar field of Vesta.
+    Pallas,
+    /// The base field of Vesta, which is the scalar field of Pallas.
+    Vesta,
+    /// The Goldilocks field of prime `2^64 - 2^32 + 1`, which isn't a curve's.
+    Goldilocks,
+    /// The base field of ed25519 (and curve25519), of prime `2^255 - 19`.
+    Ed25519,
+}
+
+// The primes in decimal, in the order of `KnownCurve::ALL`
+const PRIMES: [&str; 7] = [
+    "21888242871839275222246405745257275088548364400416034343698204186575808495617",
+    "52435875175126190479447740508185965837690552500527637822603658699938581184513",
+    "8444461749428370424248824938781546531375899335154063827935233455917409239041",
+    "28948022309329048855892746252171976963363056481941560715954676764349967630337",
+    "28948022309329048855892746252171976963363056481941647379679742748393362948097",
+    "18446744069414584321",
+    "57896044618658097711785492504343953926634992332820282019728792003956564819949",
+];
+
+thread_local! {
+    // `PRIMES`, parsed once per thread, as the toolchain predates `std::sync::OnceLock`
+    static PARSED_PRIMES: [BigInt; 7] = PRIMES
+        .iter()
+        .map(|prime| prime.parse().unwrap())
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+}
+
+impl KnownCurve {
+    /// Every known curve.
+    pub const ALL: [KnownCurve; 7] = [
+        KnownCurve::Bn254,
+        KnownCurve::Bls12_381,
+        KnownCurve::Bls12_377,
+        KnownCurve::Pallas,
+        KnownCurve::Vesta,
+        KnownCurve::Goldilocks,
+        KnownCurve::Ed25519,
+    ];
+
+    /// Returns the prime of the curve's field circuits are written over.
+    pub fn prime(&self) -> BigInt {
+        let index = Self::ALL.iter().position(|curve| curve == self).unwrap();
+        PARSED_PRIMES.with(|primes| primes[index].clone())
+    }
+
+    /// Returns the curve whose field has the prime `prime`, if it is a known one.
+    pub fn from_prime(prime: &BigInt) -> Option<Self> {
+        PARSED_PRIMES.with(|primes| {
+            let index = primes.iter().position(|known| known == prime)?;
+            Some(Self::ALL[index])
+        })
+    }
+
+    /// Returns the name of the curve, e.g. `BN254`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            KnownCurve::Bn254 => "BN254",
+            KnownCurve::Bls12_381 => "BLS12-381",
+            KnownCurve::Bls12_377 => "BLS12-377",
+            KnownCurve::Pallas => "Pallas",
+            KnownCurve::Vesta => "Vesta",
+            KnownCurve::Goldilocks => "Goldilocks",
+            KnownCurve::Ed25519 => "ed25519",
+        }
+    }
+}
+
+impl fmt::Display for KnownCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_primes() {
+        for curve in KnownCurve::ALL {
+            assert_eq!(KnownCurve::from_prime(&curve.prime()), Some(curve));
+        }
+        assert_eq!(
+            KnownCurve::Goldilocks.prime(),
+            (BigInt::from(1) << 64) - (BigInt::from(1) << 32) + 1
+        );
+        assert_eq!(KnownCurve::Ed25519.prime(), (BigInt::from(1) << 255) - 19);
+        assert_eq!(KnownCurve::from_prime(&BigInt::from(7)), None);
+        assert_eq!(KnownCurve::Bls12_381.to_string(), "BLS12-381");
+    }
+
+    #[cfg(feature = "arkworks")]
+    #[test]
+    fn bn254_scalar_field() {
+        use ark_ff::PrimeField;
+        let modulus: num_bigint::BigUint = ark_bn254::Fr::MODULUS.into();
+        assert_eq!(KnownCurve::Bn254.prime(), BigInt::from(modulus));
+    }
+}
@@ -0,0 +1,145 @@
+//! Categories of witness calculation failures
+//!
+//! The calculator returns `color_eyre` reports, which may carry context about the operation
+//! that failed. The failure itself is a [`CircomError`] somewhere in the report's chain, which
+//! [`CircomError::of`] finds to `match` on.
+use super::ExitCode;
+use color_eyre::Report;
+use std::fmt;
+
+/// Category of a failure of the witness calculator.
+///
+/// Errors caused by misusing the calculator, e.g. reading a witness before calculating one or
+/// exceeding its [`Limits`](super::Limits), are not categorized.
+#[derive(thiserror::Error, Debug)]
+pub enum CircomError {
+    /// The wasm binary couldn't be parsed or loaded into a runtime.
+    #[error("{0}")]
+    Load(String),
+    /// A host function couldn't be linked for one of the circuit's imports.
+    #[error("Failed to link {import}: {reason}")]
+    Link { import: String, reason: String },
+    /// The circuit doesn't export a function the calculator needs.
+    #[error("Unable to find function {0}")]
+    MissingExport(String),
+    /// A function of the circuit trapped.
+    #[error("{function} trapped: {reason}")]
+    Trap { function: String, reason: String },
+    /// The inputs were rejected before being passed to the circuit.
+    #[error(transparent)]
+    Input(#[from] InputError),
+    /// The circuit reported an exception through `runtime.exceptionHandler`.
+    #[error(transparent)]
+    CircomException(#[from] ExitCode),
+    /// Reading or writing a file failed.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The calculation didn't finish in time.
+    #[error("The calculation didn't finish within {0:?}")]
+    Timeout(std::time::Duration),
+}
+
+impl CircomError {
+    /// Returns the failure behind `report`, if it is a categorized one.
+    pub fn of(report: &Report) -> Option<&CircomError> {
+        report.chain().find_map(|e| e.downcast_ref::<CircomError>())
+    }
+}
+
+/// Where in the circuit's source an exception was raised, as circom reports it through
+/// `runtime.printErrorMessage`, e.g. `Error in template Num2Bits_0 line: 38` for a failed
+/// assert.
+///
+/// The message is attached as is to the error of the calculation; [`ErrorLocation::of`]
+/// recovers the location from it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorLocation {
+    /// Name of the template instance, e.g. `Num2Bits_0`.
+    pub template: String,
+    /// Line of the template's source file.
+    pub line: u32,
+}
+
+impl ErrorLocation {
+    /// Returns the location of the failure behind `report`, if the circuit reported one.
+    pub fn of(report: &Report) -> Option<ErrorLocation> {
+        report
+            .chain()
+            .find_map(|e| ErrorLocation::parse(&e.to_string()))
+    }
+
+    /// Parses the first location of a message printed by the circuit.
+    pub fn parse(message: &str) -> Option<ErrorLocation> {
+        let (_, rest) = message.split_once("Error in template ")?;
+        let (template, rest) = rest.split_once(" line: ")?;
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(rest, |end| &rest[..end]);
+        Some(ErrorLocation {
+            template: template.to_string(),
+            line: digits.parse().ok()?,
+        })
+    }
+}
+
+impl fmt::Display for ErrorLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "template {}, line {}", self.template, self.line)
+    }
+}
+
+/// Reason the inputs of a calculation were rejected.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum InputError {
+    /// The signal was provided more than once.
+    #[error("Input signal `{0}` was provided more than once")]
+    Duplicate(String),
+    /// More values than [`Limits::max_input_values`](super::Limits) were provided.
+    #[error("More than the limit of {0} input values were provided")]
+    TooManyValues(usize),
+    /// The value at `signal` (e.g. `a[0]`) isn't in `(-prime, prime)`.
+    #[error(
+        "Input signal {signal} is out of range: input has {bits} bits but field is {field_bits} bits{}",
+        if .bits == .field_bits { " (and isn't below the prime)" } else { "" }
+    )]
+    OutOfRange {
+        signal: String,
+        bits: u64,
+        field_bits: u64,
+    },
+    /// The inputs don't match the signals the circuit declares, one problem per entry.
+    #[error("Inputs don't match the circuit's signals: {}", .0.join("; "))]
+    Mismatched(Vec<String>),
+    /// The witness element at `index`, given to be encoded in `n8` bytes per element, is
+    /// negative or too wide.
+    #[error("Witness element {index} does not fit in {n8} bytes: {value}")]
+    WitnessElement {
+        index: usize,
+        value: String,
+        n8: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_location() {
+        let location = ErrorLocation::parse("Error in template Num2Bits_0 line: 38\n").unwrap();
+        assert_eq!(location.template, "Num2Bits_0");
+        assert_eq!(location.line, 38);
+        assert_eq!(location.to_string(), "template Num2Bits_0, line 38");
+
+        // the innermost template comes first when the message has several lines
+        let message =
+            "Assert Failed.\nError in template A_1 line: 5\nError in template Main_2 line: 9";
+        assert_eq!(ErrorLocation::parse(message).unwrap().template, "A_1");
+
+        assert_eq!(
+            ErrorLocation::parse("Circom exception 4: Assert Failed"),
+            None
+        );
+        assert_eq!(ErrorLocation::parse("Error in template A line: x"), None);
+    }
+}
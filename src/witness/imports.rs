@@ -0,0 +1,378 @@
+//! Import resolution for the circuit's wasm module
+//!
+//! Circom's witness generator imports a handful of host functions (by default under the
+//! `runtime` module). Instead of linking a fixed set of functions, every function import
+//! declared by the module is looked up in an [`ImportResolver`] and linked with the
+//! registered handler, if any.
+//!
+//! Handlers for circom's host functions are registered under [`RUNTIME_MODULE`], and are
+//! linked under whichever namespace the circuit actually imports them from.
+use super::CircomError;
+use color_eyre::{Report, Result};
+use std::{fmt, sync::Arc};
+use wasm3::Module;
+
+/// The module name circom's host functions are registered under.
+pub const RUNTIME_MODULE: &str = "runtime";
+
+/// Links a host function for a single `(module, name)` import of the circuit.
+///
+/// The handler receives the loaded module along with the import's module and field names,
+/// and is expected to call one of `Module::link_function` / `Module::link_closure`.
+pub type ImportHandler =
+    Arc<dyn Fn(&mut Module<'_>, &str, &str) -> wasm3::error::Result<()> + Send + Sync>;
+
+/// Table of host functions keyed by `(module, name)` import pairs.
+#[derive(Clone, Default)]
+pub struct ImportResolver {
+    handlers: Vec<(String, String, ImportHandler)>,
+}
+
+impl fmt::Debug for ImportResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.handlers
+                    .iter()
+                    .map(|(module, name, _)| format!("{}.{}", module, name)),
+            )
+            .finish()
+    }
+}
+
+impl ImportResolver {
+    /// Registers `handler` for the `module.name` import, replacing any previous handler
+    /// for the same pair.
+    pub fn register<F>(&mut self, module: &str, name: &str, handler: F)
+    where
+        F: Fn(&mut Module<'_>, &str, &str) -> wasm3::error::Result<()> + Send + Sync + 'static,
+    {
+        let handler: ImportHandler = Arc::new(handler);
+        match self
+            .handlers
+            .iter_mut()
+            .find(|(m, n, _)| m == module && n == name)
+        {
+            Some(entry) => entry.2 = handler,
+            None => self
+                .handlers
+                .push((module.to_string(), name.to_string(), handler)),
+        }
+    }
+
+    /// Returns the handler registered for the `module.name` import.
+    pub fn get(&self, module: &str, name: &str) -> Option<&ImportHandler> {
+        self.handlers
+            .iter()
+            .find(|(m, n, _)| m == module && n == name)
+            .map(|(_, _, handler)| handler)
+    }
+
+    /// Links every function import declared in `data` which has a registered handler, in
+    /// declaration order, and returns the `(module, name)` pairs of the handlers linked.
+    /// Imports without a handler are left unlinked and trap if the circuit ever calls them.
+    ///
+    /// Imports from `runtime_module` (detected with [`runtime_module`] if `None`) fall back
+    /// to the handlers registered under [`RUNTIME_MODULE`].
+    pub(crate) fn link(
+        &self,
+        module: &mut Module<'_>,
+        data: &[u8],
+        runtime_module: Option<&str>,
+    ) -> Result<Vec<(String, String)>> {
+        let mut linked = Vec::new();
+        for (module_name, name, key) in self.resolve(data, runtime_module)? {
+            let key = match key {
+                Some(key) => key,
+                None => continue,
+            };
+            if let Some(handler) = self.get(&key, &name) {
+                handler(module, &module_name, &name).map_err(|e| CircomError::Link {
+                    import: format!("{}.{}", module_name, name),
+                    reason: e.to_string(),
+                })?;
+                linked.push((key, name));
+            }
+        }
+        Ok(linked)
+    }
+
+    /// Returns every function import declared in `data` as `module.name`, along with whether
+    /// [`Self::link`] links a handler for it.
+    pub(crate) fn status(
+        &self,
+        data: &[u8],
+        runtime_module: Option<&str>,
+    ) -> Result<Vec<(String, bool)>> {
+        Ok(self
+            .resolve(data, runtime_module)?
+            .into_iter()
+            .map(|(module, name, key)| (format!("{}.{}", module, name), key.is_some()))
+            .collect())
+    }
+
+    // Returns the `(module, name)` function imports of `data` along with the module the
+    // handler for each is registered under, if any
+    fn resolve(
+        &self,
+        data: &[u8],
+        runtime_module: Option<&str>,
+    ) -> Result<Vec<(String, String, Option<String>)>> {
+        let imports = function_imports(data)?;
+        let runtime = match runtime_module {
+            Some(runtime) => runtime.to_string(),
+            None => runtime_module(&imports).unwrap_or_else(|| RUNTIME_MODULE.to_string()),
+        };
+
+        Ok(imports
+            .into_iter()
+            .map(|(module, name)| {
+                let key = if self.get(&module, &name).is_some() {
+                    Some(module.clone())
+                } else if module == runtime && self.get(RUNTIME_MODULE, &name).is_some() {
+                    Some(RUNTIME_MODULE.to_string())
+                } else {
+                    None
+                };
+                (module, name, key)
+            })
+            .collect())
+    }
+}
+
+/// Returns the namespace the circuit imports circom's host functions from, i.e. the module
+/// of its `exceptionHandler` import.
+pub(crate) fn runtime_module(imports: &[(String, String)]) -> Option<String> {
+    imports
+        .iter()
+        .find(|(_, name)| name == "exceptionHandler")
+        .map(|(module, _)| module.clone())
+}
+
+/// Returns the `(module, name)` pairs of all function imports declared by the wasm binary.
+pub(crate) fn function_imports(data: &[u8]) -> Result<Vec<(String, String)>> {
+    let mut reader = Reader { data, pos: 8 };
+    if data.len() < 8 || data[0..4] != *b"\0asm" {
+        return Err(malformed("Invalid wasm magic number"));
+    }
+
+    let mut imports = Vec::new();
+    while reader.pos < data.len() {
+        let id = reader.byte()?;
+        let size = reader.leb_u32()? as usize;
+        let end = reader.pos + size;
+        // 2 is the import section
+        if id == 2 {
+            let count = reader.leb_u32()?;
+            for _ in 0..count {
+                let module = reader.name()?;
+                let name = reader.name()?;
+                match reader.byte()? {
+                    // function: type index
+                    0 => {
+                        reader.leb_u32()?;
+                        imports.push((module, name));
+                    }
+                    // table: reftype + limits
+                    1 => {
+                        reader.byte()?;
+                        reader.limits()?;
+                    }
+                    // memory: limits
+                    2 => reader.limits()?,
+                    // global: valtype + mutability
+                    3 => {
+                        reader.byte()?;
+                        reader.byte()?;
+                    }
+                    kind => return Err(malformed(format!("Unknown wasm import kind {}", kind))),
+                }
+            }
+            break;
+        }
+        reader.pos = end;
+    }
+
+    Ok(imports)
+}
+
+/// Returns the names of all function exports of the wasm binary.
+pub(crate) fn function_exports(data: &[u8]) -> Result<Vec<String>> {
+    let mut reader = Reader { data, pos: 8 };
+    if data.len() < 8 || data[0..4] != *b"\0asm" {
+        return Err(malformed("Invalid wasm magic number"));
+    }
+
+    let mut exports = Vec::new();
+    while reader.pos < data.len() {
+        let id = reader.byte()?;
+        let size = reader.leb_u32()? as usize;
+        let end = reader.pos + size;
+        // 7 is the export section
+        if id == 7 {
+            let count = reader.leb_u32()?;
+            for _ in 0..count {
+                let name = reader.name()?;
+                let kind = reader.byte()?;
+                reader.leb_u32()?;
+                // 0 is a function, the others are tables, memories and globals
+                if kind == 0 {
+                    exports.push(name);
+                }
+            }
+            break;
+        }
+        reader.pos = end;
+    }
+
+    Ok(exports)
+}
+
+fn malformed(message: impl Into<String>) -> Report {
+    CircomError::Load(message.into()).into()
+}
+
+/// Returns the names of the custom sections of the wasm binary, e.g. `name` for the debug
+/// names of its functions.
+pub(crate) fn custom_sections(data: &[u8]) -> Result<Vec<String>> {
+    let mut reader = Reader { data, pos: 8 };
+    if data.len() < 8 || data[0..4] != *b"\0asm" {
+        return Err(malformed("Invalid wasm magic number"));
+    }
+
+    let mut names = Vec::new();
+    while reader.pos < data.len() {
+        let id = reader.byte()?;
+        let size = reader.leb_u32()? as usize;
+        let end = reader.pos + size;
+        // 0 is a custom section
+        if id == 0 {
+            names.push(reader.name()?);
+        }
+        reader.pos = end;
+    }
+
+    Ok(names)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8> {
+        let b = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| malformed("Unexpected end of wasm binary"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn leb_u32(&mut self) -> Result<u32> {
+        let mut result = 0u32;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            if shift >= 32 {
+                return Err(malformed("Invalid LEB128 value in wasm binary"));
+            }
+            result |= ((b & 0x7f) as u32) << shift;
+            if b & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+        }
+    }
+
+    fn name(&mut self) -> Result<String> {
+        let len = self.leb_u32()? as usize;
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| malformed("Unexpected end of wasm binary"))?;
+        self.pos += len;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn limits(&mut self) -> Result<()> {
+        let flags = self.byte()?;
+        self.leb_u32()?;
+        if flags & 1 == 1 {
+            self.leb_u32()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_circom2_imports() {
+        let data = std::fs::read("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        let imports = function_imports(&data).unwrap();
+        assert_eq!(
+            imports,
+            vec![
+                ("runtime".to_string(), "exceptionHandler".to_string()),
+                ("runtime".to_string(), "showSharedRWMemory".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_runtime_module() {
+        let data = std::fs::read("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        let imports = function_imports(&data).unwrap();
+        assert_eq!(runtime_module(&imports).as_deref(), Some(RUNTIME_MODULE));
+
+        let imports = vec![
+            ("env".to_string(), "log".to_string()),
+            ("circom".to_string(), "exceptionHandler".to_string()),
+        ];
+        assert_eq!(runtime_module(&imports).as_deref(), Some("circom"));
+        assert_eq!(runtime_module(&imports[..1]), None);
+    }
+
+    #[test]
+    fn reads_function_exports() {
+        let data = std::fs::read("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        let exports = function_exports(&data).unwrap();
+        assert_eq!(exports.len(), 11);
+        assert_eq!(exports[0], "getVersion");
+        assert!(!exports.iter().any(|name| name == "memory"));
+    }
+
+    #[test]
+    fn reads_custom_sections() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        assert_eq!(custom_sections(&data).unwrap(), vec!["name".to_string()]);
+        let data = std::fs::read("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        assert!(custom_sections(&data).unwrap().is_empty());
+    }
+
+    #[test]
+    fn import_status() {
+        let data = std::fs::read("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        let mut resolver = ImportResolver::default();
+        resolver.register(RUNTIME_MODULE, "exceptionHandler", |_, _, _| Ok(()));
+        assert_eq!(
+            resolver.status(&data, None).unwrap(),
+            vec![
+                ("runtime.exceptionHandler".to_string(), true),
+                ("runtime.showSharedRWMemory".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_memory_imports() {
+        let data = std::fs::read("./test-vectors/mycircuit.wasm").unwrap();
+        let imports = function_imports(&data).unwrap();
+        assert_eq!(imports.len(), 6);
+        assert!(imports.iter().all(|(module, _)| module == "runtime"));
+    }
+}
@@ -0,0 +1,238 @@
+use color_eyre::{eyre::eyre, Result};
+use num_bigint::{BigInt, Sign};
+use std::collections::{btree_map, BTreeMap, HashMap};
+use std::iter::FromIterator;
+
+/// Named input signals of a circuit.
+///
+/// The order in which input signals are written to the circuit does not affect the witness,
+/// but it does affect which error is reported first when several inputs are invalid. Unlike
+/// a `HashMap`, this type always yields signals sorted by name, so that errors are
+/// deterministic.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WitnessInputs(BTreeMap<String, Vec<BigInt>>);
+
+impl WitnessInputs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collects single-value (non-array) input signals, e.g. `[("a", 3.into()), ("b", 11.into())]`.
+    pub fn from_scalars<S: Into<String>, I: IntoIterator<Item = (S, BigInt)>>(inputs: I) -> Self {
+        Self(
+            inputs
+                .into_iter()
+                .map(|(name, value)| (name.into(), vec![value]))
+                .collect(),
+        )
+    }
+
+    /// Collects input signals given as (possibly multi-dimensional) arrays, e.g.
+    /// `[("m", vec![vec![a, b], vec![c, d]])]` for `signal input m[2][2]`. Arrays are
+    /// flattened in row-major order, which is how circom lays them out.
+    pub fn from_nested<S: Into<String>, V: Into<InputValue>, I: IntoIterator<Item = (S, V)>>(
+        inputs: I,
+    ) -> Self {
+        Self(
+            inputs
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into().flatten()))
+                .collect(),
+        )
+    }
+
+    /// Pushes a value to the input signal at the specified name.
+    pub fn push<T: Into<BigInt>>(&mut self, name: impl ToString, val: T) {
+        self.0.entry(name.to_string()).or_default().push(val.into());
+    }
+
+    /// Returns the values of the input signal at the specified name.
+    pub fn get(&self, name: &str) -> Option<&Vec<BigInt>> {
+        self.0.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<HashMap<String, Vec<BigInt>>> for WitnessInputs {
+    fn from(inputs: HashMap<String, Vec<BigInt>>) -> Self {
+        Self(inputs.into_iter().collect())
+    }
+}
+
+impl From<BTreeMap<String, Vec<BigInt>>> for WitnessInputs {
+    fn from(inputs: BTreeMap<String, Vec<BigInt>>) -> Self {
+        Self(inputs)
+    }
+}
+
+impl FromIterator<(String, Vec<BigInt>)> for WitnessInputs {
+    fn from_iter<I: IntoIterator<Item = (String, Vec<BigInt>)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl FromIterator<(String, Vec<Vec<BigInt>>)> for WitnessInputs {
+    fn from_iter<I: IntoIterator<Item = (String, Vec<Vec<BigInt>>)>>(iter: I) -> Self {
+        Self::from_nested(iter)
+    }
+}
+
+impl FromIterator<(String, BigInt)> for WitnessInputs {
+    fn from_iter<I: IntoIterator<Item = (String, BigInt)>>(iter: I) -> Self {
+        Self::from_scalars(iter)
+    }
+}
+
+/// Value of an input signal: a single value, or an array of any depth like circom's
+/// `signal input m[4][4]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputValue {
+    Value(BigInt),
+    Array(Vec<InputValue>),
+}
+
+impl InputValue {
+    /// Returns the values in row-major order, i.e. in the order circom assigns them to
+    /// consecutive positions of the signal.
+    pub fn flatten(self) -> Vec<BigInt> {
+        let mut values = Vec::new();
+        self.flatten_into(&mut values);
+        values
+    }
+
+    fn flatten_into(self, out: &mut Vec<BigInt>) {
+        match self {
+            InputValue::Value(value) => out.push(value),
+            InputValue::Array(values) => {
+                for value in values {
+                    value.flatten_into(out);
+                }
+            }
+        }
+    }
+}
+
+impl From<BigInt> for InputValue {
+    fn from(value: BigInt) -> Self {
+        InputValue::Value(value)
+    }
+}
+
+impl<T: Into<InputValue>> From<Vec<T>> for InputValue {
+    fn from(values: Vec<T>) -> Self {
+        InputValue::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Parses an input value given as a string, in decimal or as `0x` hexadecimal or `0b` binary
+/// digits, optionally negative, e.g. `-0x1f`. Surrounding whitespace is ignored. This is how
+/// the string values of `input.json` files are parsed.
+pub fn parse_bigint(s: &str) -> Result<BigInt> {
+    let invalid = || {
+        eyre!(
+            "Invalid integer {:?}, expected decimal, 0x hexadecimal or 0b binary digits",
+            s
+        )
+    };
+    let trimmed = s.trim();
+    let (sign, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => (Sign::Minus, rest),
+        None => (Sign::Plus, trimmed),
+    };
+    let (radix, digits) = match unsigned.get(..2) {
+        Some("0x") | Some("0X") => (16, &unsigned[2..]),
+        Some("0b") | Some("0B") => (2, &unsigned[2..]),
+        _ => (10, unsigned),
+    };
+    // `from_str_radix` also accepts signs and `_` separators, which circom's inputs don't
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return Err(invalid());
+    }
+    let magnitude = BigInt::parse_bytes(digits.as_bytes(), radix).ok_or_else(invalid)?;
+    Ok(if sign == Sign::Minus {
+        -magnitude
+    } else {
+        magnitude
+    })
+}
+
+impl IntoIterator for WitnessInputs {
+    type Item = (String, Vec<BigInt>);
+    type IntoIter = btree_map::IntoIter<String, Vec<BigInt>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scalars() {
+        let inputs =
+            WitnessInputs::from_scalars(vec![("a", BigInt::from(3)), ("b", BigInt::from(11))]);
+        assert_eq!(inputs.get("a"), Some(&vec![BigInt::from(3)]));
+        assert_eq!(inputs.len(), 2);
+
+        let collected: WitnessInputs = vec![("a".to_string(), BigInt::from(3))]
+            .into_iter()
+            .collect();
+        assert_eq!(collected.get("a"), inputs.get("a"));
+    }
+
+    fn values(range: std::ops::Range<i32>) -> Vec<BigInt> {
+        range.map(BigInt::from).collect()
+    }
+
+    #[test]
+    fn nested_arrays() {
+        // signal input m[2][3], flattened like snarkjs does
+        let m = vec![values(0..3), values(3..6)];
+        let inputs: WitnessInputs = vec![("m".to_string(), m)].into_iter().collect();
+        assert_eq!(inputs.get("m"), Some(&values(0..6)));
+
+        // signal input t[2][2][2]
+        let t = vec![
+            vec![values(0..2), values(2..4)],
+            vec![values(4..6), values(6..8)],
+        ];
+        let inputs = WitnessInputs::from_nested(vec![("t", t)]);
+        assert_eq!(inputs.get("t"), Some(&values(0..8)));
+
+        let ragged = InputValue::Array(vec![
+            BigInt::from(1).into(),
+            vec![BigInt::from(2), BigInt::from(3)].into(),
+        ]);
+        assert_eq!(ragged.flatten(), values(1..4));
+    }
+
+    #[test]
+    fn parses_integers() {
+        assert_eq!(parse_bigint("42").unwrap(), BigInt::from(42));
+        assert_eq!(parse_bigint(" -42\n").unwrap(), BigInt::from(-42));
+        assert_eq!(parse_bigint("0x1F").unwrap(), BigInt::from(31));
+        assert_eq!(parse_bigint("-0xff").unwrap(), BigInt::from(-255));
+        assert_eq!(parse_bigint("0b101").unwrap(), BigInt::from(5));
+        assert_eq!(parse_bigint("007").unwrap(), BigInt::from(7));
+
+        for invalid in [
+            "", "-", "0x", "0b2", "1_000", "+1", "--1", "1.5", "0xg", "12a",
+        ] {
+            let err = parse_bigint(invalid).unwrap_err();
+            assert!(
+                err.to_string().starts_with("Invalid integer"),
+                "{}",
+                invalid
+            );
+        }
+    }
+}
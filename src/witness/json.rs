@@ -0,0 +1,179 @@
+//! Streaming reader for circom's `input.json` format
+//!
+//! Inputs are parsed one signal at a time and handed over as soon as each signal's values are
+//! read, so that huge input files never have to be held in memory as a whole.
+use color_eyre::{Report, Result};
+use num_bigint::BigInt;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::{fmt, io::Read};
+
+/// Reads an object of signal names to values from `reader`, and calls `f` with every signal
+/// in document order. Arrays (of any depth) are flattened in row-major order like circom
+/// does, and values may be integers, decimal strings or booleans.
+///
+/// Stops at the first error returned by `f`, which is returned as is.
+pub(crate) fn for_each_input<R: Read>(
+    reader: R,
+    mut f: impl FnMut(String, Vec<BigInt>) -> Result<()>,
+) -> Result<()> {
+    let mut error = None;
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let parsed = deserializer
+        .deserialize_map(Signals {
+            f: &mut f,
+            error: &mut error,
+        })
+        .and_then(|_| deserializer.end());
+    match (error, parsed) {
+        (Some(e), _) => Err(e),
+        (None, Err(e)) => Err(Report::new(e).wrap_err("Invalid inputs JSON")),
+        (None, Ok(())) => Ok(()),
+    }
+}
+
+// Visits the top-level object, passing each signal on before parsing the next one
+struct Signals<'a, F> {
+    f: &'a mut F,
+    // error of `f`, which serde can only carry as a message
+    error: &'a mut Option<Report>,
+}
+
+impl<'de, 'a, F: FnMut(String, Vec<BigInt>) -> Result<()>> Visitor<'de> for Signals<'a, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an object of input signals")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(name) = map.next_key::<String>()? {
+            let mut values = Vec::new();
+            map.next_value_seed(Values {
+                name: &name,
+                out: &mut values,
+            })?;
+            if let Err(e) = (self.f)(name, values) {
+                *self.error = Some(e);
+                return Err(de::Error::custom("input rejected"));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Appends the flattened values of the signal `name` to `out`
+struct Values<'a> {
+    name: &'a str,
+    out: &'a mut Vec<BigInt>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for Values<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for Values<'a> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "integers, decimal strings or arrays of them for input {}",
+            self.name
+        )
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while seq
+            .next_element_seed(Values {
+                name: self.name,
+                out: &mut *self.out,
+            })?
+            .is_some()
+        {}
+        Ok(())
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<(), E> {
+        let value = super::parse_bigint(s)
+            .map_err(|_| E::custom(format!("Invalid value {:?} for input {}", s, self.name)))?;
+        self.out.push(value);
+        Ok(())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<(), E> {
+        self.out.push(BigInt::from(v));
+        Ok(())
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<(), E> {
+        self.out.push(BigInt::from(v));
+        Ok(())
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<(), E> {
+        Err(E::custom(format!(
+            "Input {} must be an integer, got {}",
+            self.name, v
+        )))
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<(), E> {
+        self.out.push(BigInt::from(b as u8));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::eyre::eyre;
+
+    fn collect(json: &str) -> Result<Vec<(String, Vec<BigInt>)>> {
+        let mut inputs = Vec::new();
+        for_each_input(json.as_bytes(), |name, values| {
+            inputs.push((name, values));
+            Ok(())
+        })?;
+        Ok(inputs)
+    }
+
+    #[test]
+    fn streams_signals() {
+        let inputs = collect(r#"{"b": [[1, 2], ["-4"]], "a": "0x3", "c": true}"#).unwrap();
+        assert_eq!(
+            inputs,
+            vec![
+                (
+                    "b".to_string(),
+                    vec![BigInt::from(1), BigInt::from(2), BigInt::from(-4)]
+                ),
+                ("a".to_string(), vec![BigInt::from(3)]),
+                ("c".to_string(), vec![BigInt::from(1)]),
+            ]
+        );
+
+        assert!(collect(r#"["a"]"#).is_err());
+        assert!(collect(r#"{"a": 1.5}"#).is_err());
+        assert!(collect(r#"{"a": "x"}"#).is_err());
+        assert!(collect(r#"{"a": 1} trailing"#).is_err());
+    }
+
+    #[test]
+    fn stops_at_rejected_signal() {
+        let mut seen = Vec::new();
+        let err = for_each_input(r#"{"a": 1, "b": 2, "c": 3}"#.as_bytes(), |name, _| {
+            seen.push(name.clone());
+            if name == "b" {
+                return Err(eyre!("rejected {}", name));
+            }
+            Ok(())
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "rejected b");
+        assert_eq!(seen, vec!["a".to_string(), "b".to_string()]);
+    }
+}
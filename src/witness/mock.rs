@@ -0,0 +1,226 @@
+//! A fake circuit for testing code built on the calculator without a wasm witness generator
+use super::{fnv, Circom2, CircomBase, CircomError, CircomVersion};
+use color_eyre::{eyre::eyre, Result};
+use num::Integer;
+use num_bigint::BigInt;
+use std::cell::RefCell;
+
+/// A circuit implementing circom's protocol over a canned witness, to run with
+/// [`calculate_witness_on`](super::calculate_witness_on).
+///
+/// Every value set through `setInputSignal` is recorded, so tests can check how their code
+/// assembles the inputs with [`MockCircom2::input`]. The witness is returned as is, whatever
+/// the inputs. Like circuits built by older circom versions, the circuit doesn't declare its
+/// input signals unless [`MockCircom2::with_signals`] is used.
+#[derive(Clone, Debug)]
+pub struct MockCircom2 {
+    prime: BigInt,
+    n32: u32,
+    witness: Vec<BigInt>,
+    // declared input signals and their number of values
+    signals: Option<Vec<((u32, u32), u32)>>,
+    memory: RefCell<Vec<u32>>,
+    inputs: RefCell<Vec<((u32, u32), u32, BigInt)>>,
+}
+
+impl MockCircom2 {
+    /// Creates a circuit over the field of `prime` which computes `witness`, reduced into the
+    /// field. Field elements take as many 32-bit words as `prime` needs.
+    pub fn new(prime: BigInt, witness: Vec<BigInt>) -> Self {
+        let n32 = ((prime.bits().max(1) - 1) / 32 + 1) as u32;
+        let witness = witness.iter().map(|w| w.mod_floor(&prime)).collect();
+        Self {
+            prime,
+            n32,
+            witness,
+            signals: None,
+            memory: RefCell::new(vec![0; n32 as usize]),
+            inputs: RefCell::default(),
+        }
+    }
+
+    /// Makes the circuit report field elements of `n32` words instead, e.g. to pad them like
+    /// circom does for small primes.
+    pub fn with_n32(mut self, n32: u32) -> Self {
+        self.n32 = n32;
+        *self.memory.get_mut() = vec![0; n32 as usize];
+        self
+    }
+
+    /// Makes the circuit declare `signals` as its only input signals, each along with its
+    /// number of values, so that inputs are checked against them like for a circuit exporting
+    /// `getInputSize` and `getInputSignalSize`.
+    pub fn with_signals(mut self, signals: &[(&str, u32)]) -> Self {
+        let signals = signals
+            .iter()
+            .map(|(name, size)| (fnv(name), *size))
+            .collect();
+        self.signals = Some(signals);
+        self
+    }
+
+    /// Returns the value set for `signal[index]` since the last `init`.
+    pub fn input(&self, signal: &str, index: u32) -> Option<BigInt> {
+        let hash = fnv(signal);
+        self.inputs
+            .borrow()
+            .iter()
+            .find(|(h, i, _)| *h == hash && *i == index)
+            .map(|(_, _, value)| value.clone())
+    }
+
+    /// Returns the number of values set since the last `init`.
+    pub fn input_count(&self) -> usize {
+        self.inputs.borrow().len()
+    }
+
+    fn store(&self, value: &BigInt) {
+        let (_, digits) = value.to_u32_digits();
+        let mut memory = self.memory.borrow_mut();
+        memory.iter_mut().for_each(|word| *word = 0);
+        for (word, digit) in memory.iter_mut().zip(digits) {
+            *word = digit;
+        }
+    }
+
+    fn load(&self) -> BigInt {
+        BigInt::from_slice(num_bigint::Sign::Plus, &self.memory.borrow())
+    }
+}
+
+impl CircomBase for MockCircom2 {
+    fn init(&self, _sanity_check: bool) -> Result<()> {
+        self.inputs.borrow_mut().clear();
+        Ok(())
+    }
+
+    fn get_u32(&self, name: &str) -> Result<u32> {
+        Err(CircomError::MissingExport(name.to_string()).into())
+    }
+
+    fn get_version(&self) -> Result<CircomVersion> {
+        Ok(CircomVersion::V2(2))
+    }
+}
+
+impl Circom2 for MockCircom2 {
+    fn get_field_num_len32(&self) -> Result<u32> {
+        Ok(self.n32)
+    }
+
+    fn get_raw_prime(&self) -> Result<()> {
+        self.store(&self.prime);
+        Ok(())
+    }
+
+    fn read_shared_rw_memory(&self, i: u32) -> Result<u32> {
+        self.memory
+            .borrow()
+            .get(i as usize)
+            .copied()
+            .ok_or_else(|| eyre!("Shared memory index {} is out of bounds", i))
+    }
+
+    fn write_shared_rw_memory(&self, i: u32, v: u32) -> Result<()> {
+        let mut memory = self.memory.borrow_mut();
+        let word = memory
+            .get_mut(i as usize)
+            .ok_or_else(|| eyre!("Shared memory index {} is out of bounds", i))?;
+        *word = v;
+        Ok(())
+    }
+
+    fn set_input_signal(&self, hmsb: u32, hlsb: u32, pos: u32) -> Result<()> {
+        let value = self.load();
+        self.inputs.borrow_mut().push(((hmsb, hlsb), pos, value));
+        Ok(())
+    }
+
+    fn get_witness(&self, i: u32) -> Result<()> {
+        let value = self
+            .witness
+            .get(i as usize)
+            .ok_or_else(|| eyre!("Witness index {} is out of bounds", i))?;
+        self.store(value);
+        Ok(())
+    }
+
+    fn get_witness_size(&self) -> Result<u32> {
+        Ok(self.witness.len() as u32)
+    }
+
+    fn get_input_size(&self) -> Result<Option<u32>> {
+        Ok(self
+            .signals
+            .as_ref()
+            .map(|signals| signals.iter().map(|(_, size)| size).sum()))
+    }
+
+    fn get_input_signal_size(&self, hmsb: u32, hlsb: u32) -> Result<Option<u32>> {
+        Ok(self.signals.as_ref().map(|signals| {
+            signals
+                .iter()
+                .find(|(hash, _)| *hash == (hmsb, hlsb))
+                .map_or(0, |(_, size)| *size)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witness::{calculate_witness_on, CircomRuntime};
+
+    #[test]
+    fn canned_witness() {
+        let prime = BigInt::parse_bytes(
+            b"21888242871839275222246405745257275088548364400416034343698204186575808495617",
+            10,
+        )
+        .unwrap();
+        let witness = [1, 33, 3, 11].map(BigInt::from).to_vec();
+        let mock = MockCircom2::new(prime.clone(), witness.clone());
+        assert_eq!(mock.prime().unwrap(), prime);
+
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(3)]),
+            ("b".to_string(), vec![BigInt::from(-1)]),
+        ];
+        assert_eq!(calculate_witness_on(&mock, inputs, true).unwrap(), witness);
+        assert_eq!(mock.input_count(), 2);
+        assert_eq!(mock.input("a", 0), Some(BigInt::from(3)));
+        assert_eq!(mock.input("b", 0), Some(&prime - 1));
+        assert_eq!(mock.input("b", 1), None);
+    }
+
+    #[test]
+    fn declared_signals() {
+        let mock = MockCircom2::new(BigInt::from(101), vec![BigInt::from(1)])
+            .with_signals(&[("a", 1), ("m", 4)]);
+        assert_eq!(mock.get_input_size().unwrap(), Some(5));
+        let (msb, lsb) = fnv("m");
+        assert_eq!(mock.get_input_signal_size(msb, lsb).unwrap(), Some(4));
+
+        let inputs = vec![("c".to_string(), vec![BigInt::from(3)])];
+        assert!(calculate_witness_on(&mock, inputs, true).is_err());
+        assert_eq!(mock.input_count(), 0);
+    }
+
+    #[test]
+    fn positions_follow_flattening() {
+        let prime = BigInt::from(101);
+        let mock = MockCircom2::new(prime, vec![BigInt::from(1)]);
+
+        // signal input m[2][2]
+        let m = vec![
+            vec![BigInt::from(1), BigInt::from(2)],
+            vec![BigInt::from(3), BigInt::from(4)],
+        ];
+        let inputs = crate::witness::WitnessInputs::from_nested(vec![("m", m)]);
+        calculate_witness_on(&mock, inputs, true).unwrap();
+        assert_eq!(mock.input_count(), 4);
+        for position in 0..4 {
+            assert_eq!(mock.input("m", position), Some(BigInt::from(position + 1)));
+        }
+    }
+}
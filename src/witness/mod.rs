@@ -1,10 +1,62 @@
 mod witness_calculator;
-pub use witness_calculator::WitnessCalculator;
+pub use witness_calculator::{
+    calculate_witness_on, report_exception, CircuitCost, ExitCode, Limits, PartialWitness,
+    WitnessCalculator, WitnessError,
+};
 
-mod circom;
-pub(super) use circom::{CircomBase, Wasm};
+#[cfg(feature = "groth16")]
+pub use witness_calculator::Groth16Assignment;
+
+mod error;
+pub use error::{CircomError, ErrorLocation, InputError};
+
+mod wtns;
+pub use wtns::{witness_from_blob, witness_json, witness_to_blob, write_wtns, write_wtns_file};
+
+mod sym;
+pub use sym::{read_sym, Symbol};
+
+mod curve;
+pub use curve::KnownCurve;
+
+mod prepared;
+pub use prepared::PreparedCircuit;
+
+#[cfg(feature = "bundle")]
+mod bundle;
+#[cfg(feature = "bundle")]
+pub use bundle::CircuitBundle;
+
+mod registry;
+pub use registry::CircuitRegistry;
 
-pub(super) use circom::Circom2;
+mod pack;
+pub use pack::{pack_witness, unpack_witness};
+
+mod inputs;
+pub use inputs::{parse_bigint, InputValue, WitnessInputs};
+
+#[cfg(feature = "json")]
+mod json;
+
+#[cfg(feature = "json")]
+mod cli;
+#[cfg(feature = "json")]
+pub use cli::{wtns_calculate, InputSource};
+
+#[cfg(any(test, feature = "test-util"))]
+mod mock;
+#[cfg(feature = "test-util")]
+pub use mock::MockCircom2;
+
+mod imports;
+pub use imports::{ImportHandler, ImportResolver, RUNTIME_MODULE};
+
+mod circom;
+#[cfg(feature = "call-stats")]
+pub use circom::CallStats;
+pub use circom::{Circom2, CircomBase, CircomRuntime, CircomVersion};
+pub(super) use circom::{InitState, Wasm};
 
 use fnv::FnvHasher;
 use std::hash::Hasher;
@@ -0,0 +1,137 @@
+//! Post-processing of computed witnesses
+use color_eyre::{eyre::eyre, Result};
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, Zero};
+
+/// Packs `witness` into fewer, wider integers, e.g. to hash or commit to it with fewer field
+/// elements: every `elements_per_group` consecutive elements, each taking at most
+/// `element_bits` bits, are concatenated into one integer, the first element in the least
+/// significant bits. The last group holds fewer elements if the witness length is not a
+/// multiple of `elements_per_group`.
+///
+/// Every packed integer is below `2^(element_bits * elements_per_group)`, so it is an element
+/// of any field whose prime is at least that; [`unpack_witness`] recovers the witness.
+///
+/// Returns an error if an element is negative or doesn't fit in `element_bits` bits, e.g. a
+/// witness which wasn't reduced into the field or whose elements are wider than assumed.
+///
+/// # Panics
+///
+/// Panics if `element_bits` or `elements_per_group` is 0.
+pub fn pack_witness(
+    witness: &[BigInt],
+    element_bits: u64,
+    elements_per_group: usize,
+) -> Result<Vec<BigInt>> {
+    assert!(element_bits > 0, "element_bits must be non-zero");
+    assert!(
+        elements_per_group > 0,
+        "elements_per_group must be non-zero"
+    );
+    witness
+        .chunks(elements_per_group)
+        .enumerate()
+        .map(|(group, elements)| {
+            let mut packed = BigInt::zero();
+            for (i, element) in elements.iter().enumerate().rev() {
+                if element.sign() == Sign::Minus || element.bits() > element_bits {
+                    return Err(eyre!(
+                        "Witness element {} doesn't fit in {} bits: {}",
+                        group * elements_per_group + i,
+                        element_bits,
+                        element
+                    ));
+                }
+                packed = (packed << element_bits) + element;
+            }
+            Ok(packed)
+        })
+        .collect()
+}
+
+/// Unpacks the first `len` elements of a witness packed by [`pack_witness`] with the same
+/// `element_bits` and `elements_per_group`.
+///
+/// Returns an error if `packed` holds fewer than `len` elements, or if a packed integer is
+/// negative or wider than a group.
+///
+/// # Panics
+///
+/// Panics if `element_bits` or `elements_per_group` is 0.
+pub fn unpack_witness(
+    packed: &[BigInt],
+    element_bits: u64,
+    elements_per_group: usize,
+    len: usize,
+) -> Result<Vec<BigInt>> {
+    assert!(element_bits > 0, "element_bits must be non-zero");
+    assert!(
+        elements_per_group > 0,
+        "elements_per_group must be non-zero"
+    );
+    if packed.len() < (len + elements_per_group - 1) / elements_per_group {
+        return Err(eyre!(
+            "{} packed integers can't hold {} elements",
+            packed.len(),
+            len
+        ));
+    }
+    let mask = (BigInt::one() << element_bits) - 1;
+    let mut witness = Vec::with_capacity(len);
+    for (group, value) in packed.iter().enumerate() {
+        if value.sign() == Sign::Minus || value.bits() > element_bits * elements_per_group as u64 {
+            return Err(eyre!("Packed integer {} is not a group: {}", group, value));
+        }
+        let mut value = value.clone();
+        for _ in 0..elements_per_group {
+            if witness.len() == len {
+                return Ok(witness);
+            }
+            witness.push(&value & &mask);
+            value >>= element_bits;
+        }
+    }
+    Ok(witness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let witness = (1..=5).map(BigInt::from).collect::<Vec<_>>();
+        let packed = pack_witness(&witness, 8, 2).unwrap();
+        assert_eq!(
+            packed,
+            vec![BigInt::from(0x0201), BigInt::from(0x0403), BigInt::from(5)]
+        );
+        assert_eq!(unpack_witness(&packed, 8, 2, 5).unwrap(), witness);
+        assert!(pack_witness(&[], 8, 3).unwrap().is_empty());
+
+        // full-width elements, like those of the BN254 field
+        let prime = BigInt::parse_bytes(
+            b"21888242871839275222246405745257275088548364400416034343698204186575808495617",
+            10,
+        )
+        .unwrap();
+        let witness = vec![&prime - 1, BigInt::from(0), &prime - 2];
+        let packed = pack_witness(&witness, 254, 3).unwrap();
+        assert_eq!(packed.len(), 1);
+        assert!(packed[0].bits() <= 3 * 254);
+        assert_eq!(unpack_witness(&packed, 254, 3, 3).unwrap(), witness);
+    }
+
+    #[test]
+    fn rejects_wide_elements() {
+        let err = pack_witness(&[BigInt::from(1), BigInt::from(256)], 8, 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Witness element 1 doesn't fit in 8 bits: 256"
+        );
+        assert!(pack_witness(&[BigInt::from(-1)], 8, 2).is_err());
+
+        assert!(unpack_witness(&[BigInt::from(1)], 8, 2, 3).is_err());
+        assert!(unpack_witness(&[BigInt::from(1) << 16], 8, 2, 2).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! Two-phase witness calculation: load a circuit once, run it many times
+use super::WitnessCalculator;
+use color_eyre::Result;
+use num_bigint::BigInt;
+
+/// A circuit which is parsed, linked and initialized ahead of time, so that every
+/// [`run`](Self::run) only writes the inputs and reads back the witness, then resets the
+/// circuit with `init` for the next run. A run after the circuit was used otherwise, e.g.
+/// through [`calculator`](Self::calculator), runs `init` first itself.
+///
+/// The reset leaves the input signals of the previous run in memory, like any reused
+/// instance, so every run must provide all of the circuit's inputs; see
+/// [`WitnessCalculator::reinit`].
+#[derive(Debug)]
+pub struct PreparedCircuit {
+    wtns: WitnessCalculator,
+}
+
+impl PreparedCircuit {
+    /// Loads the witness generator wasm in `data` and prepares its instance.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        WitnessCalculator::from_bytes(data)?.prepare()
+    }
+
+    /// Calculates the witness with the sanity check flag stored on the calculator the circuit
+    /// was prepared from. The circuit is reset afterwards, also when the calculation fails.
+    pub fn run<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+    ) -> Result<Vec<BigInt>> {
+        self.wtns
+            .calculate_prepared(inputs, self.wtns.sanity_check())
+    }
+
+    /// Returns the calculator running the circuit, e.g. to read its prime.
+    pub fn calculator(&self) -> &WitnessCalculator {
+        &self.wtns
+    }
+
+    /// Returns the calculator running the circuit, keeping its loaded instance. Its
+    /// calculations run `init` again themselves.
+    pub fn into_calculator(self) -> WitnessCalculator {
+        self.wtns
+    }
+}
+
+impl WitnessCalculator {
+    /// Loads and initializes the circuit's instance now rather than on the first calculation,
+    /// returning it as a [`PreparedCircuit`].
+    pub fn prepare(self) -> Result<PreparedCircuit> {
+        self.reinit(self.sanity_check())?;
+        Ok(PreparedCircuit { wtns: self })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_many_times() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let circuit = PreparedCircuit::load(&data).unwrap();
+        for (a, b) in [(3, 11), (2, 5)] {
+            let inputs = vec![
+                ("a".to_string(), vec![BigInt::from(a)]),
+                ("b".to_string(), vec![BigInt::from(b)]),
+            ];
+            let witness = circuit.run(inputs).unwrap();
+            assert_eq!(witness[1], BigInt::from(a * b));
+        }
+        #[cfg(feature = "call-stats")]
+        assert_eq!(circuit.calculator().call_stats().unwrap().calls("init"), 1);
+
+        // a failed run leaves the circuit ready for the next one
+        let unknown = vec![("c".to_string(), vec![BigInt::from(3)])];
+        assert!(circuit.run(unknown).is_err());
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(4)]),
+            ("b".to_string(), vec![BigInt::from(6)]),
+        ];
+        assert_eq!(circuit.run(inputs).unwrap()[1], BigInt::from(24));
+    }
+
+    #[test]
+    fn runs_after_other_calculations() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let circuit = PreparedCircuit::load(&data).unwrap();
+        let inputs = |a: u32, b: u32| {
+            vec![
+                ("a".to_string(), vec![BigInt::from(a)]),
+                ("b".to_string(), vec![BigInt::from(b)]),
+            ]
+        };
+        assert_eq!(circuit.run(inputs(3, 11)).unwrap()[1], BigInt::from(33));
+
+        // the calculation leaves the instance used, so the next run initializes it again
+        let witness = circuit
+            .calculator()
+            .calculate_witness(inputs(2, 5), true)
+            .unwrap();
+        assert_eq!(witness[1], BigInt::from(10));
+        assert_eq!(circuit.run(inputs(4, 6)).unwrap()[1], BigInt::from(24));
+        #[cfg(feature = "call-stats")]
+        assert_eq!(circuit.calculator().call_stats().unwrap().calls("init"), 2);
+
+        circuit.calculator().reset();
+        assert_eq!(circuit.run(inputs(7, 3)).unwrap()[1], BigInt::from(21));
+    }
+}
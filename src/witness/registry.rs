@@ -0,0 +1,92 @@
+//! Dispatching witness calculations to one of several loaded circuits
+use super::WitnessCalculator;
+use color_eyre::{eyre::eyre, Result};
+use num_bigint::BigInt;
+use std::collections::HashMap;
+
+/// A set of circuits keyed by name, for services which compute witnesses for several
+/// statements.
+///
+/// Every circuit is a [`WitnessCalculator`] owning its wasm3 environment, so circuits don't
+/// interfere with each other.
+#[derive(Clone, Debug, Default)]
+pub struct CircuitRegistry {
+    circuits: HashMap<String, WitnessCalculator>,
+}
+
+impl CircuitRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the witness generator wasm in `data` as the circuit `name`, replacing any circuit
+    /// previously registered under that name.
+    pub fn register(&mut self, name: impl Into<String>, data: &[u8]) -> Result<()> {
+        let wtns = WitnessCalculator::from_bytes(data)?;
+        self.circuits.insert(name.into(), wtns);
+        Ok(())
+    }
+
+    /// Returns the calculator of the circuit `name`, e.g. to change its settings.
+    pub fn get(&self, name: &str) -> Option<&WitnessCalculator> {
+        self.circuits.get(name)
+    }
+
+    /// Mutable version of [`Self::get`].
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut WitnessCalculator> {
+        self.circuits.get_mut(name)
+    }
+
+    /// Removes the circuit `name`, returning its calculator.
+    pub fn remove(&mut self, name: &str) -> Option<WitnessCalculator> {
+        self.circuits.remove(name)
+    }
+
+    /// Returns the names of the registered circuits, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.circuits.keys().map(String::as_str)
+    }
+
+    /// Calculates the witness of the circuit `name` with its stored sanity check flag, see
+    /// [`WitnessCalculator::calculate`].
+    pub fn calculate<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        name: &str,
+        inputs: I,
+    ) -> Result<Vec<BigInt>> {
+        self.circuits
+            .get(name)
+            .ok_or_else(|| eyre!("No circuit registered as `{}`", name))?
+            .calculate(inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatches_by_name() {
+        let mut registry = CircuitRegistry::new();
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        registry.register("multiplier", &data).unwrap();
+        let data = std::fs::read("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        registry.register("multiplier_v2", &data).unwrap();
+
+        let inputs = || {
+            vec![
+                ("a".to_string(), vec![BigInt::from(3)]),
+                ("b".to_string(), vec![BigInt::from(11)]),
+            ]
+        };
+        for name in ["multiplier", "multiplier_v2"] {
+            let witness = registry.calculate(name, inputs()).unwrap();
+            assert_eq!(witness[1], BigInt::from(33));
+        }
+
+        let err = registry.calculate("missing", inputs()).unwrap_err();
+        assert!(err.to_string().contains("`missing`"));
+        assert!(registry.remove("multiplier").is_some());
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["multiplier_v2"]);
+    }
+}
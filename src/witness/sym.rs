@@ -0,0 +1,198 @@
+//! Parsing of circom's `.sym` files, which map witness indices to signal names
+use super::CircomError;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use num_bigint::BigInt;
+use std::{convert::TryFrom, io::BufRead, path::Path};
+
+/// A line of a `.sym` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    /// Index of the signal in the circuit.
+    pub label_index: usize,
+    /// Index of the signal in the witness, `None` if it was optimized out.
+    pub witness_index: Option<usize>,
+    /// Id of the component declaring the signal.
+    pub component: usize,
+    /// Full name of the signal, e.g. `main.out[3]`.
+    pub name: String,
+}
+
+/// Reads the symbols of a `.sym` file in its
+/// `label index,witness index,component id,name` format.
+pub fn read_sym<R: BufRead>(reader: R) -> Result<Vec<Symbol>> {
+    let mut symbols = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let symbol =
+            parse_symbol(&line).wrap_err_with(|| format!("Invalid .sym line {}", number + 1))?;
+        symbols.push(symbol);
+    }
+    Ok(symbols)
+}
+
+// Reads the `.sym` file at `path`
+pub(crate) fn read_sym_file(path: &Path) -> Result<Vec<Symbol>> {
+    let file = std::fs::File::open(path)
+        .map_err(CircomError::Io)
+        .wrap_err_with(|| format!("Unable to open symbol file {}", path.display()))?;
+    read_sym(std::io::BufReader::new(file))
+}
+
+// Returns the witness index of the signal `name`
+pub(crate) fn witness_index(symbols: &[Symbol], name: &str) -> Result<u32> {
+    let symbol = symbols
+        .iter()
+        .find(|symbol| symbol.name == name)
+        .ok_or_else(|| eyre!("Signal {} is not in the symbol table", name))?;
+    let index = symbol
+        .witness_index
+        .ok_or_else(|| eyre!("Signal {} was optimized out of the witness", name))?;
+    u32::try_from(index).map_err(|_| eyre!("Signal {} has witness index {}", name, index))
+}
+
+// Returns the distinct names of the signals of the main component, e.g. `in` for both
+// `main.in[0]` and `main.in[1]`, in the order of `symbols`
+pub(crate) fn main_signals(symbols: &[Symbol]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for symbol in symbols {
+        let name = match symbol.name.strip_prefix("main.") {
+            Some(name) if !name.contains('.') => name,
+            _ => continue,
+        };
+        let name = name.split('[').next().unwrap_or(name);
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+// Returns the number of public outputs of the circuit, given the names of its input signals:
+// circom places the outputs at the start of the witness, right after the constant 1 and
+// before the inputs, so they end where the first input begins. `None` if no input is in the
+// witness
+pub(crate) fn num_outputs<'a>(
+    symbols: &[Symbol],
+    inputs: impl IntoIterator<Item = &'a str> + Clone,
+) -> Option<usize> {
+    symbols
+        .iter()
+        .filter(|symbol| {
+            let name = match symbol.name.strip_prefix("main.") {
+                Some(name) if !name.contains('.') => name,
+                _ => return false,
+            };
+            let name = name.split('[').next().unwrap_or(name);
+            inputs.clone().into_iter().any(|input| input == name)
+        })
+        .filter_map(|symbol| symbol.witness_index)
+        .min()
+        .map(|first_input| first_input - 1)
+}
+
+// Labels the witness elements with the names of `symbols`, in their order, skipping the
+// signals which were optimized out
+pub(crate) fn label_witness(
+    symbols: &[Symbol],
+    witness: &[BigInt],
+) -> Result<Vec<(String, BigInt)>> {
+    symbols
+        .iter()
+        .filter_map(|symbol| Some((symbol.witness_index?, symbol.name.clone())))
+        .map(|(index, name)| {
+            let value = witness.get(index).cloned().ok_or_else(|| {
+                eyre!(
+                    "Signal {} has witness index {} but the witness has {} elements",
+                    name,
+                    index,
+                    witness.len()
+                )
+            })?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+fn parse_symbol(line: &str) -> Result<Symbol> {
+    let mut columns = line.splitn(4, ',');
+    let mut column = |what: &str| {
+        columns
+            .next()
+            .map(str::trim)
+            .ok_or_else(|| eyre!("missing {}", what))
+    };
+
+    let label_index = column("label index")?.parse()?;
+    let witness_index = column("witness index")?.parse::<i64>()?;
+    let component = column("component id")?.parse()?;
+    let name = column("signal name")?.to_string();
+
+    Ok(Symbol {
+        label_index,
+        witness_index: usize::try_from(witness_index).ok(),
+        component,
+        name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lines() {
+        let sym = "1,1,0,main.out[3]\n2,-1,1,main.sub.x\n\n";
+        let symbols = read_sym(sym.as_bytes()).unwrap();
+        assert_eq!(
+            symbols,
+            vec![
+                Symbol {
+                    label_index: 1,
+                    witness_index: Some(1),
+                    component: 0,
+                    name: "main.out[3]".to_string(),
+                },
+                Symbol {
+                    label_index: 2,
+                    witness_index: None,
+                    component: 1,
+                    name: "main.sub.x".to_string(),
+                },
+            ]
+        );
+
+        let err = read_sym("1,2\n".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+
+        assert_eq!(witness_index(&symbols, "main.out[3]").unwrap(), 1);
+        let err = witness_index(&symbols, "main.sub.x").unwrap_err();
+        assert!(err.to_string().contains("optimized out"));
+        witness_index(&symbols, "main.y").unwrap_err();
+    }
+
+    #[test]
+    fn main_signal_names() {
+        let sym = "1,1,0,main.in[0]\n2,2,0,main.in[1]\n3,3,1,main.sub.x\n4,4,0,main.out\n";
+        let symbols = read_sym(sym.as_bytes()).unwrap();
+        assert_eq!(
+            main_signals(&symbols),
+            vec!["in".to_string(), "out".to_string()]
+        );
+    }
+
+    #[test]
+    fn outputs_before_inputs() {
+        let sym = "1,1,0,main.out[0]\n2,2,0,main.out[1]\n3,3,0,main.in[0]\n4,4,0,main.in[1]\n\
+                   5,5,1,main.sub.in\n";
+        let symbols = read_sym(sym.as_bytes()).unwrap();
+        assert_eq!(num_outputs(&symbols, ["in"].iter().copied()), Some(2));
+        assert_eq!(num_outputs(&symbols, ["out"].iter().copied()), Some(0));
+        assert_eq!(num_outputs(&symbols, ["x"].iter().copied()), None);
+    }
+}
@@ -1,196 +1,4187 @@
-use super::{fnv, CircomBase, Wasm};
-use color_eyre::Result;
-use num_bigint::BigInt;
-use num_traits::Zero;
+use super::circom::check_n32;
+#[cfg(feature = "call-stats")]
+use super::CallStats;
+use super::{
+    fnv, CircomBase, CircomError, CircomRuntime, CircomVersion, ImportResolver, InitState,
+    InputError, KnownCurve, Symbol, Wasm, RUNTIME_MODULE,
+};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Report, Result,
+};
+use num_bigint::{BigInt, BigUint, Sign};
 
-use num::ToPrimitive;
-use wasm3::{Environment, Module};
+use num::Integer;
+use std::{
+    cell::{Cell, Ref, RefCell, RefMut},
+    collections::HashSet,
+    convert::TryFrom,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Weak,
+    },
+};
+
+#[cfg(any(feature = "groth16", feature = "r1cs"))]
+use crate::circom::R1CS;
+use wasm3::{Environment, Module, Runtime};
 
 use super::Circom2;
 
+/// Calculates witnesses with a circuit's witness generator wasm, run in wasm3.
+///
+/// Cloning a calculator is cheap: clones share the wasm bytes and the metadata read when it
+/// was loaded, but not the circuit's instance. Every clone creates its own wasm3 runtime on
+/// first use, so clones can be moved to separate threads and run concurrently, e.g. one per
+/// worker of a server. The wasm3 runtime never leaves the thread which loaded it: a
+/// calculator moved to another thread loads a new instance there on first use, like a clone.
 #[derive(Clone, Debug)]
 pub struct WitnessCalculator {
-    pub data: Vec<u8>,
+    // the circuit's wasm, shared by clones
+    data: Arc<[u8]>,
     pub n64: u32,
+    // read from the circuit once, when it is loaded; `n64`, input conversion, reduction and
+    // `.wtns` output all use this value
+    prime: BigInt,
+    // `None` until the circuit is loaded for a calculator whose prime was supplied by the
+    // caller, which then also checks the prime
+    metadata: Cell<Option<Metadata>>,
+    // words of a field element supplied by the caller along with the prime, checked with it
+    n32: Option<u32>,
+    imports: ImportResolver,
+    sanity_check: bool,
+    direct_memory: bool,
+    exit_on_exception: bool,
+    reproducible: bool,
+    // whether the wasm carries a `name` section
+    name_section: bool,
+    runtime_module: Option<String>,
+    limits: Limits,
+    messages: Option<MessageSink>,
+    instance: InstanceCache,
+    // thread of `calculate_witness_with_timeout`, not shared by clones
+    timeout_worker: TimeoutWorker,
+    // whether calculations keep their inputs for `update_input_and_recompute`
+    cache_inputs: bool,
+    // inputs and sanity check flag of the last successful `calculate_witness`, if cached
+    last_inputs: RefCell<Option<LastInputs>>,
+    symbols: Option<Arc<[Symbol]>>,
+    // input signals of the main component and their sizes, read from the circuit when the
+    // symbols are set; `None` without symbols or if the circuit doesn't report the sizes
+    input_schema: Option<Arc<[(String, usize)]>>,
+}
+
+type LastInputs = (Vec<(String, Vec<BigInt>)>, bool);
+
+// Metadata read from the circuit's exports when it is loaded
+#[derive(Clone, Copy, Debug)]
+struct Metadata {
+    version: CircomVersion,
+    input_size: Option<u32>,
+    witness_size: u32,
+}
+
+impl Metadata {
+    fn read<C: CircomBase + Circom2>(instance: &C) -> Result<Self> {
+        Ok(Metadata {
+            version: instance.get_version()?,
+            input_size: instance.get_input_size()?,
+            witness_size: instance.get_witness_size()?,
+        })
+    }
+}
+
+/// Error of a single witness calculation.
+pub type WitnessError = color_eyre::Report;
+
+/// Error type to signal end of execution.
+///
+/// The circuit reports fatal conditions (unknown signal, failed assert, ...) by calling the
+/// `runtime.exceptionHandler` import with a code. Circom keeps running after the call, so the
+/// code is recorded and the calculation is ended with
+/// [`CircomError::CircomException`]`(ExitCode(code))` at the next check. The message the
+/// circuit printed through `runtime.printErrorMessage`, e.g. the template and line of a failed
+/// assert, is attached to that error as context, which
+/// [`ErrorLocation::of`](super::ErrorLocation::of) reads the location from.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Circom exception {0}: {}", exit_message(.0))]
+pub struct ExitCode(pub u32);
+
+impl ExitCode {
+    /// Returns the message circom's JS witness calculator uses for this code.
+    pub fn message(&self) -> &'static str {
+        exit_message(&self.0)
+    }
+}
+
+fn exit_message(code: &u32) -> &'static str {
+    match *code {
+        1 => "Signal not found",
+        2 => "Too many signals set",
+        3 => "Signal already set",
+        4 => "Assert Failed",
+        5 => "Not enough memory",
+        6 => "Input signal array access exceeds the size",
+        _ => "Unknown error",
+    }
+}
+
+// A circuit loaded into its own runtime. Functions are found through the runtime, so that the
+// module handle (which borrows the runtime) doesn't need to be stored.
+struct Instance {
+    rt: Runtime,
+    _env: Environment,
+    // where the instance is in circom's protocol
+    state: Cell<InitState>,
+    // calls made by the last operation on the instance
+    #[cfg(feature = "call-stats")]
+    stats: RefCell<CallStats>,
+}
+
+impl Instance {
+    // Parses the module, loads it into a runtime of `runtime_size` bytes and links `imports`.
+    // Both reading the metadata and calculating go through here, so that the circuit is always
+    // set up the same way.
+    fn load(
+        data: &[u8],
+        imports: &ImportResolver,
+        runtime_module: Option<&str>,
+        runtime_size: u32,
+        reproducible: bool,
+    ) -> Result<Self> {
+        let load_error = |what: &str, e: wasm3::error::Error| {
+            CircomError::Load(format!("Unable to {}: {}", what, e))
+        };
+        let env = Environment::new().map_err(|e| load_error("create environment", e))?;
+        let rt = env
+            .create_runtime(runtime_size)
+            .map_err(|e| load_error("create runtime", e))?;
+
+        {
+            let module = Module::parse(&env, data).map_err(|e| load_error("parse module", e))?;
+            let mut module = rt
+                .load_module(module)
+                .map_err(|e| load_error("load module", e))?;
+            let linked = imports.link(&mut module, data, runtime_module)?;
+            if reproducible {
+                check_link_order(&linked)?;
+            }
+        }
+
+        Ok(Instance {
+            rt,
+            _env: env,
+            state: Cell::new(InitState::Uninitialized),
+            #[cfg(feature = "call-stats")]
+            stats: RefCell::default(),
+        })
+    }
+
+    // Size in bytes of the module's linear memory
+    fn memory_size(&self) -> usize {
+        // SAFETY: no wasm function is executing, and the slice doesn't outlive the call
+        unsafe { (*self.rt.memory()).len() }
+    }
+
+    // The wasm3 runtime the module is loaded in
+    fn runtime(&self) -> &Runtime {
+        &self.rt
+    }
+
+    fn wasm(&self, direct_memory: bool) -> Wasm<'_> {
+        let wasm = Wasm::new(&self.rt).tracking_init(&self.state);
+        #[cfg(feature = "call-stats")]
+        let wasm = wasm.counting_calls(&self.stats);
+        if direct_memory {
+            wasm
+        } else {
+            wasm.without_direct_memory()
+        }
+    }
+}
+
+// Size of the runtime used to read the version and prime when loading a circuit, which only
+// runs a few small exports. The large runtime calculations need is only created on first use.
+// Both are set up by `Instance::load`.
+const METADATA_RUNTIME_SIZE: u32 = 1024 * 60;
+
+// Names of the `runtime` host functions, in the order circom declares them
+const RUNTIME_FUNCTIONS: [&str; 4] = [
+    "exceptionHandler",
+    "printErrorMessage",
+    "writeBufferMessage",
+    "showSharedRWMemory",
+];
+
+// Checks that only `runtime` functions were linked, in their canonical order
+fn check_link_order(linked: &[(String, String)]) -> Result<()> {
+    let mut last = None;
+    for (module, name) in linked {
+        let position = RUNTIME_FUNCTIONS
+            .iter()
+            .position(|f| module == RUNTIME_MODULE && f == name)
+            .ok_or_else(|| CircomError::Link {
+                import: format!("{}.{}", module, name),
+                reason: "not allowed in reproducible mode".to_string(),
+            })?;
+        if last.map_or(false, |last| position <= last) {
+            return Err(CircomError::Link {
+                import: format!("{}.{}", module, name),
+                reason: "linked out of order in reproducible mode".to_string(),
+            }
+            .into());
+        }
+        last = Some(position);
+    }
+    Ok(())
+}
+
+// The instance loaded by a calculator, which is never shared with its clones.
+//
+// A wasm3 runtime must stay on the thread which loaded it: the host functions linked into it
+// need not be `Send`, and the circuit reports exceptions through thread-locals. Instances are
+// therefore kept in a table of that thread, under the cache's token, so that the calculator
+// itself can move between threads. Entries of dropped caches are evicted by the next access
+// on their thread.
+struct InstanceCache {
+    token: Arc<()>,
+}
+
+// An instance slot of a cache on one thread. It sits in a `RefCell` so that calculations only
+// need `&self`.
+type InstanceSlot = Rc<RefCell<Option<Instance>>>;
+
+thread_local! {
+    // slots of the caches which were used on this thread
+    static INSTANCES: RefCell<Vec<(Weak<()>, InstanceSlot)>> = RefCell::new(Vec::new());
+}
+
+impl InstanceCache {
+    // Returns the slot of the cache on the current thread, creating it on first use
+    fn slot(&self) -> InstanceSlot {
+        INSTANCES.with(|instances| {
+            let mut instances = instances.borrow_mut();
+            instances.retain(|(token, _)| token.strong_count() > 0);
+            match instances.iter().find(|(token, _)| self.owns(token)) {
+                Some((_, slot)) => slot.clone(),
+                None => {
+                    let slot = InstanceSlot::default();
+                    instances.push((Arc::downgrade(&self.token), slot.clone()));
+                    slot
+                }
+            }
+        })
+    }
+
+    // Returns the slot of the cache on the current thread, if it was used on it
+    fn find(&self) -> Option<InstanceSlot> {
+        INSTANCES.with(|instances| {
+            let instances = instances.try_borrow().ok()?;
+            instances
+                .iter()
+                .find(|(token, _)| self.owns(token))
+                .map(|(_, slot)| slot.clone())
+        })
+    }
+
+    fn owns(&self, token: &Weak<()>) -> bool {
+        Weak::as_ptr(token) == Arc::as_ptr(&self.token)
+    }
+}
+
+fn borrow_slot(slot: &InstanceSlot) -> Result<Ref<'_, Option<Instance>>> {
+    slot.try_borrow().map_err(|_| not_reentrant())
+}
+
+fn borrow_slot_mut(slot: &InstanceSlot) -> Result<RefMut<'_, Option<Instance>>> {
+    slot.try_borrow_mut().map_err(|_| not_reentrant())
+}
+
+fn not_reentrant() -> color_eyre::Report {
+    eyre!("The witness calculator was called again while running a calculation")
+}
+
+impl Default for InstanceCache {
+    fn default() -> Self {
+        Self {
+            token: Arc::new(()),
+        }
+    }
+}
+
+impl Clone for InstanceCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl Drop for InstanceCache {
+    fn drop(&mut self) {
+        // free the instance of this thread right away, the others on their next access
+        let _ = INSTANCES.try_with(|instances| {
+            if let Ok(mut instances) = instances.try_borrow_mut() {
+                instances.retain(|(token, _)| !self.owns(token));
+            }
+        });
+    }
+}
+
+impl std::fmt::Debug for InstanceCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let slot = self.find();
+        f.write_str(match slot.as_ref().map(|slot| slot.try_borrow()) {
+            Some(Ok(instance)) if instance.is_some() => "InstanceCache(loaded)",
+            Some(Err(_)) => "InstanceCache(running)",
+            _ => "InstanceCache(empty)",
+        })
+    }
+}
+
+// The thread which runs the calculations of `calculate_witness_with_timeout`. It is started
+// on first use and reused by the later calls, so that calculations which never return leave
+// at most one thread behind per calculator. It exits once idle after the calculator is
+// dropped.
+#[derive(Debug, Default)]
+struct TimeoutWorker {
+    // jobs for the thread, and whether it is running one
+    thread: RefCell<Option<(mpsc::Sender<TimeoutJob>, Arc<AtomicBool>)>>,
+}
+
+// A calculation for the timeout worker: the calculator to run it with, its inputs and sanity
+// check flag, and where to send the witness along with the inputs the calculator cached
+type TimeoutJob = (
+    WitnessCalculator,
+    Vec<(String, Vec<BigInt>)>,
+    bool,
+    mpsc::Sender<(Result<Vec<BigInt>>, Option<LastInputs>)>,
+);
+
+impl TimeoutWorker {
+    // Hands `job` to the thread, starting it if needed. Fails with a timeout of `timeout` if
+    // the thread is still running an earlier job.
+    fn run(&self, job: TimeoutJob, timeout: std::time::Duration) -> Result<()> {
+        let mut thread = self.thread.borrow_mut();
+        if let Some((_, busy)) = thread.as_ref() {
+            if busy.load(Ordering::SeqCst) {
+                return Err(Report::new(CircomError::Timeout(timeout))
+                    .wrap_err("An earlier calculation which timed out is still running"));
+            }
+        }
+        let (jobs, busy) = thread.get_or_insert_with(|| {
+            let (jobs, receiver) = mpsc::channel::<TimeoutJob>();
+            let busy = Arc::new(AtomicBool::new(false));
+            let worker_busy = busy.clone();
+            std::thread::spawn(move || {
+                for (wtns, inputs, sanity_check, sender) in receiver {
+                    let witness = wtns.calculate_witness(inputs, sanity_check);
+                    let last_inputs = wtns.last_inputs.into_inner();
+                    worker_busy.store(false, Ordering::SeqCst);
+                    // nobody is listening anymore if the calculation timed out
+                    let _ = sender.send((witness, last_inputs));
+                }
+            });
+            (jobs, busy)
+        });
+        busy.store(true, Ordering::SeqCst);
+        if jobs.send(job).is_err() {
+            // the thread panicked, the next call starts a new one
+            *thread = None;
+            return Err(eyre!("The calculation thread stopped"));
+        }
+        Ok(())
+    }
+
+    // Forgets the thread after its calculation panicked, so that the next call starts a new
+    // one
+    fn reset(&self) {
+        *self.thread.borrow_mut() = None;
+    }
+}
+
+impl Clone for TimeoutWorker {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// Bounds on what a circuit may make the calculator allocate, for services running circuits
+/// they don't trust.
+///
+/// The limits cover the host side of a calculation: the witness read back from the circuit
+/// and the inputs written to it. The memory of the circuit itself is bounded separately, by
+/// the size of the wasm3 runtime and the memory limits the module declares, and nothing
+/// bounds the time a calculation takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of witness elements, `1 << 27` by default.
+    pub max_witness_size: u32,
+    /// Maximum number of input values over all input signals, `1 << 24` by default.
+    pub max_input_values: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_witness_size: 1 << 27,
+            max_input_values: 1 << 24,
+        }
+    }
+}
+
+/// Size of a circuit, as far as it can be told from its metadata without calculating a
+/// witness, to estimate what generating and proving a witness will take.
+///
+/// As a rule of thumb, the witness calculation is linear in [`Self::witness_size`], and so
+/// is a Groth16 proof, which is dominated by multi-scalar multiplications over the witness:
+/// expect in the order of 10µs per witness signal on a single core for a 254-bit field,
+/// i.e. around 10s for a million signals. Both grow with the number of 64-bit words of the
+/// field, and the proof parallelizes well across cores. The proving key takes a few hundred
+/// bytes per witness signal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitCost {
+    /// Number of bits of the circuit's prime.
+    pub field_bits: u64,
+    /// Number of bytes of a field element in the witness, as in a `.wtns` file.
+    pub field_bytes: u32,
+    /// Number of witness signals, including the leading constant one.
+    pub witness_size: u32,
+    /// Total number of input values, if the circuit reports it, see
+    /// [`WitnessCalculator::input_size`].
+    pub input_size: Option<u32>,
+}
+
+impl CircuitCost {
+    /// Returns the size in bytes of the witness as field elements, e.g. of the witness
+    /// section of a `.wtns` file.
+    pub fn witness_bytes(&self) -> u64 {
+        self.witness_size as u64 * self.field_bytes as u64
+    }
+}
+
+/// A witness read from the circuit even though it reported an exception, see
+/// [`WitnessCalculator::calculate_witness_partial`].
+#[derive(Debug)]
+pub struct PartialWitness {
+    /// The witness, of the circuit's full witness size. After an exception, elements the
+    /// circuit didn't get to compute hold whatever they were initialized with, usually 0.
+    pub witness: Vec<BigInt>,
+    /// The exception which interrupted the calculation, with the error message the circuit
+    /// printed along with it, see [`CircomError::of`] and
+    /// [`ErrorLocation::of`](super::ErrorLocation::of). `None` if the calculation succeeded.
+    pub exception: Option<Report>,
+}
+
+// Settings of a single calculation
+#[derive(Clone, Copy, Debug)]
+struct RunOptions<'a> {
+    prime: &'a BigInt,
+    limits: Limits,
+    sanity_check: bool,
+    exit_on_exception: bool,
+    messages: Option<&'a MessageSink>,
+}
+
+// Receives the messages the circuit prints which don't end up in an error, see
+// `WitnessCalculator::set_message_handler`
+#[derive(Clone)]
+struct MessageSink(Arc<dyn Fn(&str) + Send + Sync>);
+
+impl std::fmt::Debug for MessageSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MessageSink")
+    }
+}
+
+// Checks that `data` is a wasm module, naming the circom and snarkjs files it is commonly
+// confused with. Empty or truncated data is rejected here rather than left to the wasm
+// parser, e.g. for a zero-byte upload.
+fn check_wasm(data: &[u8]) -> Result<()> {
+    if data.is_empty() {
+        return Err(CircomError::Load("The file is empty, not a wasm module".to_string()).into());
+    }
+    if data.starts_with(b"\0asm") {
+        if data.len() < 8 {
+            return Err(
+                CircomError::Load("The wasm module header is truncated".to_string()).into(),
+            );
+        }
+        return Ok(());
+    }
+    let kind = match data.get(..4) {
+        Some(b"r1cs") => "an .r1cs constraint system",
+        Some(b"zkey") => "a .zkey proving key",
+        Some(b"wtns") => "a .wtns witness",
+        Some(b"PK\x03\x04") => "a zip archive",
+        _ => return Err(CircomError::Load("The file is not a wasm module".to_string()).into()),
+    };
+    Err(CircomError::Load(format!(
+        "The file is {}, not a wasm witness generator",
+        kind
+    ))
+    .into())
+}
+
+// circom 2 exports the calculator can't do without
+const REQUIRED_EXPORTS: [&str; 8] = [
+    "init",
+    "getFieldNumLen32",
+    "getRawPrime",
+    "readSharedRWMemory",
+    "writeSharedRWMemory",
+    "setInputSignal",
+    "getWitness",
+    "getWitnessSize",
+];
+
+// Checks that the circuit exports circom 2's witness generator interface, and only once, so
+// that a non-standard build fails to load rather than failing at whichever call first misses
+// its export, or running whichever entry point is found first
+fn check_exports(data: &[u8]) -> Result<()> {
+    let exports = super::imports::function_exports(data)?;
+    let exported = |name: &str| exports.iter().any(|export| export == name);
+
+    if !exported("getFieldNumLen32") && exported("getFrLen") {
+        return Err(CircomError::Load(
+            "The circuit was compiled with circom 1, whose witness generator isn't supported"
+                .to_string(),
+        )
+        .into());
+    }
+    if let Some(name) = REQUIRED_EXPORTS.iter().find(|name| !exported(name)) {
+        return Err(CircomError::MissingExport(name.to_string()).into());
+    }
+
+    // e.g. `main2_init` next to `init` in a custom build bundling several main components
+    for entry_point in ["init", "getWitness"] {
+        let others = exports
+            .iter()
+            .filter(|export| {
+                export
+                    .strip_suffix(entry_point)
+                    .map_or(false, |prefix| prefix.ends_with(&['_', '.', '$'][..]))
+            })
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !others.is_empty() {
+            return Err(CircomError::Load(format!(
+                "The circuit exports several witness generators ({}, {}), but only circuits \
+                 with a single main component are supported",
+                entry_point,
+                others.join(", ")
+            ))
+            .into());
+        }
+    }
+    Ok(())
 }
 
-// Error type to signal end of execution.
-// From https://docs.wasmer.io/integrations/examples/exit-early
-#[derive(thiserror::Error, Debug, Clone, Copy)]
-#[error("{0}")]
-struct ExitCode(u32);
+fn sha256(data: &[u8]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(data).into()
+}
 
-fn from_array32(arr: Vec<u32>) -> BigInt {
-    let mut res = BigInt::zero();
-    let radix = BigInt::from(0x100000000u64);
-    for &val in arr.iter() {
-        res = res * &radix + BigInt::from(val);
+#[cfg(feature = "arkworks")]
+fn to_field_elements<F: ark_ff::PrimeField>(witness: &[BigInt]) -> Result<Vec<F>> {
+    let modulus: BigUint = F::MODULUS.into();
+    let mut elements = Vec::with_capacity(witness.len());
+    for w in witness {
+        elements.push(to_field_element(w, &modulus)?);
     }
-    res
+    Ok(elements)
 }
 
-fn to_array32(s: &BigInt, size: usize) -> Vec<u32> {
-    let mut res = vec![0; size];
-    let mut rem = s.clone();
-    let radix = BigInt::from(0x100000000u64);
-    let mut c = size;
-    while !rem.is_zero() {
-        c -= 1;
-        res[c] = (&rem % &radix).to_u32().unwrap();
-        rem /= &radix;
+// Converts a witness element in `(-modulus, modulus)` to a field element, mapping negative
+// values to their additive inverse
+#[cfg(feature = "arkworks")]
+fn to_field_element<F: ark_ff::PrimeField>(w: &BigInt, modulus: &BigUint) -> Result<F> {
+    let magnitude = w.magnitude();
+    if magnitude >= modulus {
+        return Err(eyre!(
+            "Witness value {} is out of range for a field of modulus {}",
+            w,
+            modulus
+        ));
     }
+    Ok(F::from(if w.sign() == Sign::Minus {
+        modulus - magnitude
+    } else {
+        magnitude.clone()
+    }))
+}
 
-    res
+// Writes the non-negative `value` to `limbs`, least significant word first like the circuit
+// stores field elements
+fn write_limbs_le(value: &BigInt, limbs: &mut [u32]) {
+    limbs.iter_mut().for_each(|limb| *limb = 0);
+    for (limb, digit) in limbs.iter_mut().zip(value.iter_u32_digits()) {
+        *limb = digit;
+    }
 }
 
 impl WitnessCalculator {
+    /// Size in bytes of the wasm3 runtime (stack) every calculation runs in, which
+    /// [`Self::set_reproducible`] pins.
+    pub const REPRODUCIBLE_RUNTIME_SIZE: u32 = 1024 * 1000000;
+
     pub fn new(path: impl AsRef<std::path::Path>) -> Result<Self> {
         Self::from_file(path)
     }
 
+    /// Loads the circuit from its witness generator wasm at `path`, whatever its file name.
+    /// The file is recognized by its content, and common mix-ups like passing the circuit's
+    /// `.r1cs` or `.zkey` file are reported as such.
     pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
-        let data = std::fs::read(path).expect("Unable to read file");
-        Self::from_bytes(&data)
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(CircomError::Io)?;
+        Self::from_bytes(&data).wrap_err_with(|| format!("Unable to load {}", path.display()))
     }
 
-    pub fn from_bytes(data: &Vec<u8>) -> Result<Self> {
-        let env = Environment::new().expect("Unable to create environment");
-        let rt = env
-            .create_runtime(1024 * 60)
-            .expect("Unable to create runtime");
-        let module = Module::parse(&env, &data[..]).expect("Unable to parse module");
+    /// Loads the circuit from the bytes of its witness generator wasm, e.g. as embedded with
+    /// `WitnessCalculator::from_bytes(include_bytes!("circuit.wasm"))`.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        check_wasm(data)?;
+        check_exports(data)?;
+        let imports = runtime_imports();
+        let loaded = Instance::load(data, &imports, None, METADATA_RUNTIME_SIZE, false)?;
+        let instance = loaded.wasm(true);
+        let metadata = Metadata::read(&instance)?;
+        // `getRawPrime` only copies a constant to the shared memory, so the prime can be read
+        // without running `init`
+        let prime = instance.prime()?;
+
+        Self::with_metadata(data, prime, Some(metadata), imports)
+    }
 
-        let module = rt.load_module(module).expect("Unable to load module");
-        let instance = Wasm::new(module);
-        let n32 = instance.get_field_num_len32()?;
-        instance.get_raw_prime()?;
-        let mut arr = vec![0; n32 as usize];
-        for i in 0..n32 {
-            let res = instance.read_shared_rw_memory(i)?;
-            arr[(n32 as usize) - (i as usize) - 1] = res;
+    /// Like [`Self::from_bytes`], but trusts the caller-supplied `prime` of the circuit's field
+    /// instead of reading it from the circuit, so that no runtime is created until the first
+    /// calculation. The prime is checked against the circuit when it is first loaded, failing
+    /// that calculation if they don't match.
+    pub fn from_bytes_with_prime(data: &[u8], prime: BigInt) -> Result<Self> {
+        check_wasm(data)?;
+        check_exports(data)?;
+        Self::with_metadata(data, prime, None, runtime_imports())
+    }
+
+    /// Like [`Self::from_bytes_with_prime`], but also trusts the caller-supplied number of
+    /// 32-bit words of the circuit's field elements (circom's `n32`, 8 for a 254-bit prime).
+    /// Both are checked against the circuit when it is first loaded, failing that calculation
+    /// if they don't match.
+    ///
+    /// This suits `wasm32` targets, where every runtime is especially costly to create: with
+    /// the field known up front, the circuit is only instantiated once, for the first
+    /// calculation.
+    pub fn from_bytes_with_field(data: &[u8], prime: BigInt, n32: u32) -> Result<Self> {
+        check_n32(n32)?;
+        if prime.bits() > 32 * n32 as u64 {
+            return Err(eyre!(
+                "Field elements of {} words can't hold the {}-bit prime",
+                n32,
+                prime.bits()
+            ));
         }
-        let prime = from_array32(arr);
+        let mut wtns = Self::from_bytes_with_prime(data, prime)?;
+        wtns.n32 = Some(n32);
+        Ok(wtns)
+    }
 
+    fn with_metadata(
+        data: &[u8],
+        prime: BigInt,
+        metadata: Option<Metadata>,
+        imports: ImportResolver,
+    ) -> Result<Self> {
+        // whether supplied or read from a corrupt circuit, a prime of 0 or 1 would leave no
+        // room for field elements
+        if prime <= BigInt::from(1) {
+            return Err(CircomError::Load(format!("{} is not a valid prime", prime)).into());
+        }
         let n64 = ((prime.bits() - 1) / 64 + 1) as u32;
+        let name_section = super::imports::custom_sections(data)?
+            .iter()
+            .any(|name| name == "name");
 
         Ok(WitnessCalculator {
-            data: data.clone(),
+            data: Arc::from(data),
             n64,
+            prime,
+            metadata: Cell::new(metadata),
+            n32: None,
+            imports,
+            sanity_check: false,
+            direct_memory: true,
+            exit_on_exception: true,
+            reproducible: false,
+            name_section,
+            runtime_module: None,
+            limits: Limits::default(),
+            messages: None,
+            instance: InstanceCache::default(),
+            timeout_worker: TimeoutWorker::default(),
+            cache_inputs: false,
+            last_inputs: RefCell::default(),
+            symbols: None,
+            input_schema: None,
         })
     }
 
-    pub fn calculate_witness_element<
+    /// Like [`Self::from_bytes`], but first checks that the SHA-256 hash of `data` is
+    /// `expected_hash`, to pin the circuit to a known-good build.
+    pub fn from_bytes_verified(data: &[u8], expected_hash: &[u8; 32]) -> Result<Self> {
+        let hash = sha256(data);
+        if &hash != expected_hash {
+            return Err(CircomError::Load(format!(
+                "Circuit hash {} does not match the expected {}",
+                hex::encode(hash),
+                hex::encode(expected_hash)
+            ))
+            .into());
+        }
+        Self::from_bytes(data)
+    }
+
+    /// Returns every function the circuit imports, as `module.name`, along with whether a
+    /// host function is linked for it, e.g. to spot an import the crate doesn't provide when
+    /// a circuit fails to instantiate. Imports without a host function trap if called.
+    pub fn import_status(&self) -> Result<Vec<(String, bool)>> {
+        self.imports
+            .status(&self.data, self.runtime_module.as_deref())
+    }
+
+    /// Returns the circuit's wasm, as loaded.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the SHA-256 hash of the circuit's wasm.
+    pub fn circuit_hash(&self) -> [u8; 32] {
+        sha256(&self.data)
+    }
+
+    /// Returns the circom version the circuit was compiled with.
+    ///
+    /// For a calculator created with [`Self::from_bytes_with_prime`], this loads the circuit
+    /// if no calculation ran yet.
+    pub fn version(&self) -> Result<CircomVersion> {
+        Ok(self.metadata()?.version)
+    }
+
+    /// Returns the total number of input values of the circuit, across all of its input
+    /// signals, e.g. to preallocate input buffers. Returns `None` for circuits which don't
+    /// export `getInputSize`, i.e. ones built with circom < 2.1.
+    ///
+    /// Like [`Self::version`], this loads the circuit for a calculator created with
+    /// [`Self::from_bytes_with_prime`] if no calculation ran yet.
+    pub fn input_size(&self) -> Result<Option<u32>> {
+        Ok(self.metadata()?.input_size)
+    }
+
+    /// Returns a one-line description of the circuit for logs and command line tools, e.g.
+    /// `circom 2 circuit, 254-bit prime field, 2 inputs, 4 witness signals`. The number of
+    /// inputs is left out for circuits which don't report it.
+    pub fn summary(&self) -> Result<String> {
+        let metadata = self.metadata()?;
+        let version = match metadata.version {
+            CircomVersion::V1 => "circom 1".to_string(),
+            CircomVersion::V2(major) => format!("circom {}", major),
+        };
+        let inputs = match metadata.input_size {
+            Some(size) => format!(", {} inputs", size),
+            None => String::new(),
+        };
+        Ok(format!(
+            "{} circuit, {}-bit prime field{}, {} witness signals",
+            version,
+            self.prime.bits(),
+            inputs,
+            metadata.witness_size
+        ))
+    }
+
+    /// Returns the size of the circuit from its metadata, without calculating a witness, see
+    /// [`CircuitCost`] for estimating resource needs from it.
+    ///
+    /// Like [`Self::version`], this loads the circuit for a calculator created with
+    /// [`Self::from_bytes_with_prime`] if no calculation ran yet.
+    pub fn cost(&self) -> Result<CircuitCost> {
+        let metadata = self.metadata()?;
+        Ok(CircuitCost {
+            field_bits: self.prime.bits(),
+            field_bytes: self.n64 * 8,
+            witness_size: metadata.witness_size,
+            input_size: metadata.input_size,
+        })
+    }
+
+    fn metadata(&self) -> Result<Metadata> {
+        match self.metadata.get() {
+            Some(metadata) => Ok(metadata),
+            // loading the circuit reads and checks the metadata
+            None => self.with_wasm(|instance| Metadata::read(instance)),
+        }
+    }
+
+    /// Returns whether the circuit's wasm carries the `name` section naming its functions,
+    /// as left by builds which don't strip the generated wasm. This is only a heuristic for a
+    /// debug build: such builds are larger and may run slower, but a stripped circuit isn't
+    /// necessarily an optimized one.
+    ///
+    /// circom doesn't mark debug builds, neither in the exports nor otherwise: whether asserts
+    /// fire depends on the sanity check flag of each calculation, not on how the circuit was
+    /// built.
+    pub fn has_name_section(&self) -> bool {
+        self.name_section
+    }
+
+    /// Returns the prime of the circuit's field.
+    pub fn prime(&self) -> &BigInt {
+        &self.prime
+    }
+
+    /// Returns the well-known curve whose field the circuit is compiled for, e.g.
+    /// [`KnownCurve::Bn254`] for circom's default prime, or `None` for an unusual field, whose
+    /// arithmetic no curve library may provide.
+    pub fn detected_curve(&self) -> Option<KnownCurve> {
+        KnownCurve::from_prime(&self.prime)
+    }
+
+    /// Returns the prime as little-endian bytes, zero-padded to exactly `n64 * 8` bytes as in
+    /// the `.wtns` header.
+    pub fn prime_le_bytes(&self) -> Vec<u8> {
+        let (_, mut bytes) = self.prime.to_bytes_le();
+        bytes.resize(self.n64 as usize * 8, 0);
+        bytes
+    }
+
+    /// Calculates the witness and formats it like `snarkjs wtns export json`, see
+    /// [`witness_json`](super::witness_json).
+    pub fn calculate_witness_json<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<String> {
+        let witness = self.calculate_witness(inputs, sanity_check)?;
+        Ok(super::witness_json(&witness))
+    }
+
+    /// Writes `witness` in snarkjs' `.wtns` format using the circuit's prime, see
+    /// [`write_wtns`](super::write_wtns).
+    pub fn write_wtns<W: std::io::Write>(&self, witness: &[BigInt], writer: W) -> Result<()> {
+        super::write_wtns(witness, &self.prime, self.n64, writer)
+    }
+
+    /// Writes `witness` in snarkjs' `.wtns` format to a file at `path`.
+    pub fn write_wtns_file(
+        &self,
+        witness: &[BigInt],
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<()> {
+        super::write_wtns_file(witness, &self.prime, self.n64, path)
+    }
+
+    /// Registers a host function handler for the `module.name` import of the circuit.
+    ///
+    /// Handlers are linked before `init` for every import the circuit declares, which allows
+    /// running circuits built with imports beyond the standard `runtime` functions. Registering
+    /// a handler for one of the `runtime` functions replaces the default one.
+    ///
+    /// Handlers are `Send + Sync` because instances are loaded on whichever thread the
+    /// calculator (or a clone of it) runs, e.g. by [`Self::calculate_witness_with_timeout`].
+    /// The host functions they link stay on the thread of their instance.
+    ///
+    /// ```rust,ignore
+    /// wtns.register_import("env", "log", |module, m, n| {
+    ///     module.link_function::<i32, ()>(m, n, log_wrap)
+    /// });
+    /// ```
+    pub fn register_import<F>(&mut self, module: &str, name: &str, handler: F)
+    where
+        F: Fn(&mut Module<'_>, &str, &str) -> wasm3::error::Result<()> + Send + Sync + 'static,
+    {
+        self.imports.register(module, name, handler);
+    }
+
+    /// Sets the namespace the circuit imports circom's host functions from, overriding the
+    /// one detected from its `exceptionHandler` import. The handlers registered under
+    /// `runtime` are linked under this namespace.
+    ///
+    /// Changing the namespace drops the loaded instance.
+    pub fn set_runtime_module(&mut self, module: impl Into<String>) {
+        self.runtime_module = Some(module.into());
+        self.reset();
+    }
+
+    /// Sets the bounds on the witness size and number of input values calculations are
+    /// allowed to reach, see [`Limits`].
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Sets a handler for the messages the circuit prints which don't end up in an error:
+    /// the output of its `log` calls, and the error messages of exceptions which are ignored
+    /// (see [`Self::set_exit_on_exception`]). Messages are dropped without a handler; the
+    /// error message of a failed calculation is always attached to its error.
+    ///
+    /// The circuit's buffer only holds its latest message, so of several `log` calls between
+    /// two input signals only the last one is received.
+    pub fn set_message_handler<F: Fn(&str) + Send + Sync + 'static>(&mut self, handler: F) {
+        self.messages = Some(MessageSink(Arc::new(handler)));
+    }
+
+    /// Returns the sanity check flag used by [`Self::calculate`] and [`Self::calculate_element`].
+    pub fn sanity_check(&self) -> bool {
+        self.sanity_check
+    }
+
+    /// Sets the sanity check flag passed to the circuit's `init` by [`Self::calculate`] and
+    /// [`Self::calculate_element`]. Defaults to `false`.
+    ///
+    /// The flag only enables circom's extra sanity checks. Exceptions the circuit raises (an
+    /// unknown signal, a failed assert, ...) are captured and fail the calculation either way,
+    /// which is controlled separately by [`Self::set_exit_on_exception`].
+    pub fn set_sanity_check(&mut self, sanity_check: bool) {
+        self.sanity_check = sanity_check;
+    }
+
+    /// Enables (the default) or disables writing inputs and reading the witness directly
+    /// through the circuit's shared memory window. When disabled, every 32-bit word goes
+    /// through a `writeSharedRWMemory` / `readSharedRWMemory` call, which is mostly useful
+    /// to measure the per-call overhead.
+    pub fn set_direct_memory_access(&mut self, enabled: bool) {
+        self.direct_memory = enabled;
+    }
+
+    /// Calculates the witness using the stored sanity check flag.
+    pub fn calculate<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+    ) -> Result<Vec<BigInt>> {
+        self.calculate_witness(inputs, self.sanity_check)
+    }
+
+    /// Calculates the witness as field elements using the stored sanity check flag.
+    #[cfg(feature = "arkworks")]
+    pub fn calculate_element<
         E: ark_ec::pairing::Pairing,
         I: IntoIterator<Item = (String, Vec<BigInt>)>,
     >(
-        &mut self,
+        &self,
         inputs: I,
-        sanity_check: bool,
     ) -> Result<Vec<E::ScalarField>> {
-        use ark_ff::PrimeField;
-        let env = Environment::new().expect("Unable to create environment");
-        let rt = env
-            .create_runtime(1024 * 1000000)
-            .expect("Unable to create runtime");
-
-        let module = Module::parse(&env, &self.data[..]).expect("Unable to parse module");
-
-        let mut module = rt.load_module(module).expect("Unable to load module");
-        module
-            .link_function::<i32, ()>("runtime", "exceptionHandler", exception_handler_wrap)
-            .expect("Failed to link runtime.exceptionHandler");
-
-        // Link printErrorMessage function
-        module
-            .link_function::<(), ()>("runtime", "printErrorMessage", print_error_message_wrap)
-            .expect("Failed to link runtime.printErrorMessage");
-
-        // Link writeBufferMessage function
-        module
-            .link_function::<(), ()>("runtime", "writeBufferMessage", write_buffer_message_wrap)
-            .expect("Failed to link runtime.writeBufferMessage");
-
-        // Link showSharedRWMemory function
-        module
-            .link_function::<(), ()>("runtime", "showSharedRWMemory", show_shared_rw_memory_wrap)
-            .expect("Failed to link runtime.showSharedRWMemory");
-        let instance = Wasm::new(module);
-        instance.init(sanity_check)?;
-
-        let n32 = instance.get_field_num_len32()?;
-
-        // allocate the inputs
-        for (name, values) in inputs.into_iter() {
-            let (msb, lsb) = fnv(&name);
-
-            for (i, value) in values.into_iter().enumerate() {
-                let f_arr = to_array32(&value, n32 as usize);
-                for j in 0..n32 {
-                    instance.write_shared_rw_memory(j, f_arr[(n32 as usize) - 1 - (j as usize)])?;
-                }
-                instance.set_input_signal(msb, lsb, i as u32)?;
-            }
+        self.calculate_witness_element::<E, _>(inputs, self.sanity_check)
+    }
+
+    /// Calculates the witness for the given named input signals.
+    ///
+    /// The circuit is loaded on first use and the instance is reused by later calculations,
+    /// see [`Self::reinit`] for what this means for the inputs.
+    ///
+    /// The order in which signals are yielded by `inputs` does not affect the witness, so any
+    /// map type can be passed directly. Use [`WitnessInputs`](super::WitnessInputs) to also get
+    /// a deterministic order for error reporting.
+    ///
+    /// The order of the values of a signal does matter: value `i` is set at position `i` of the
+    /// signal, so the values of an array signal must be its elements flattened in row-major
+    /// order, which is how circom lays them out (see
+    /// [`WitnessInputs::from_nested`](super::WitnessInputs::from_nested)).
+    ///
+    /// Calculations only borrow the calculator immutably, so it can be kept in an `Rc` shared
+    /// by event handlers. They are not re-entrant though: calling into the same calculator
+    /// while it is running, e.g. from an import handler, returns an error.
+    pub fn calculate_witness<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        self.calculate_witness_with_progress(inputs, sanity_check, |_, _| {})
+    }
+
+    /// Re-runs the circuit with `inputs` and returns whether the witness matches
+    /// `expected_witness`, e.g. one calculated earlier from the same inputs, to surface any
+    /// nondeterminism when fuzzing or auditing.
+    ///
+    /// The calculation runs on a freshly loaded instance, so that no state left by earlier
+    /// calculations can hide or cause a difference, and doesn't touch the calculator's own
+    /// instance or cached inputs. Its errors are returned as is. Use
+    /// [`Self::first_nondeterministic_index`] to find where the witnesses differ.
+    pub fn verify_deterministic<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        expected_witness: &[BigInt],
+        sanity_check: bool,
+    ) -> Result<bool> {
+        Ok(self
+            .first_nondeterministic_index(inputs, expected_witness, sanity_check)?
+            .is_none())
+    }
+
+    /// Like [`Self::verify_deterministic`], but returns the index of the first element of the
+    /// witness which differs from `expected_witness`, or `None` if they're identical. A
+    /// witness of a different size differs past the end of the shorter one.
+    pub fn first_nondeterministic_index<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        expected_witness: &[BigInt],
+        sanity_check: bool,
+    ) -> Result<Option<usize>> {
+        let instance = self.load_instance()?;
+        let witness = run(
+            &instance.wasm(self.direct_memory),
+            inputs,
+            self.options(sanity_check),
+        )?;
+        Ok((0..witness.len().max(expected_witness.len()))
+            .find(|&i| witness.get(i) != expected_witness.get(i)))
+    }
+
+    /// Like [`Self::calculate_witness`], but reports how far reading the witness got by
+    /// calling `progress` with the number of elements read and the witness size, e.g. to show
+    /// a progress bar. It is called about every percent of the witness, and always once all
+    /// of it is read.
+    pub fn calculate_witness_with_progress<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+        progress: impl FnMut(usize, usize),
+    ) -> Result<Vec<BigInt>> {
+        let options = self.options(sanity_check);
+        if !self.cache_inputs {
+            return self
+                .with_wasm(|instance| run_with_progress(instance, inputs, options, progress));
         }
+        let inputs = inputs.into_iter().collect::<Vec<_>>();
+        let witness = self.with_wasm(|instance| {
+            run_with_progress(instance, inputs.iter().cloned(), options, progress)
+        })?;
+        *self.last_inputs.borrow_mut() = Some((inputs, sanity_check));
+        Ok(witness)
+    }
 
-        let mut witness = Vec::new();
+    /// Makes [`Self::calculate_witness`] (and the calculations built on it) keep a copy of
+    /// their inputs for [`Self::update_input_and_recompute`], which costs a copy of the
+    /// inputs per calculation. Off by default; turning it off drops the cached inputs.
+    pub fn set_input_caching(&mut self, enabled: bool) {
+        self.cache_inputs = enabled;
+        if !enabled {
+            *self.last_inputs.get_mut() = None;
+        }
+    }
 
-        let witness_size = instance.get_witness_size()?;
-        for i in 0..witness_size {
-            instance.get_witness(i)?;
-            let mut arr = vec![0; n32 as usize];
-            for j in 0..n32 {
-                arr[(n32 as usize) - 1 - (j as usize)] = instance.read_shared_rw_memory(j)?;
-            }
-            witness.push(from_array32(arr));
+    /// Calculates the witness for inputs in circom's `input.json` format read from `reader`,
+    /// writing every signal to the circuit as soon as it is parsed instead of first parsing
+    /// the whole file, e.g. for huge input arrays. Arrays (of any depth) are flattened in
+    /// row-major order like circom does.
+    ///
+    /// The inputs are never held as a whole, so they don't become the cached inputs of
+    /// [`Self::update_input_and_recompute`].
+    #[cfg(feature = "json")]
+    pub fn calculate_witness_from_json<R: std::io::Read>(
+        &self,
+        reader: R,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        let options = self.options(sanity_check);
+        self.with_wasm(|instance| {
+            let mut scratch = Vec::new();
+            let mut writer = InputWriter::new(instance, options, &mut scratch)?;
+            super::json::for_each_input(reader, |name, values| writer.write(&name, &values))?;
+            read_witness(instance, options, &mut scratch, |_, _| {})
+        })
+    }
+
+    /// Recalculates the witness with the inputs of the last [`Self::calculate_witness`]
+    /// (including the calculations built on it), except for the input signal `name` which
+    /// is set to `values`, e.g. to explore how the outputs depend on a single input. Needs
+    /// the inputs to be cached, see [`Self::set_input_caching`].
+    ///
+    /// The circuit is re-run from `init` with all of the inputs, using the sanity check flag
+    /// of the last calculation. On success, the updated inputs become the cached ones.
+    pub fn update_input_and_recompute(
+        &self,
+        name: &str,
+        values: Vec<BigInt>,
+    ) -> Result<Vec<BigInt>> {
+        if !self.cache_inputs {
+            return Err(eyre!(
+                "Inputs aren't cached, enable it with `set_input_caching` first"
+            ));
         }
+        let (mut inputs, sanity_check) = self
+            .last_inputs
+            .borrow()
+            .clone()
+            .ok_or_else(|| eyre!("No witness has been calculated yet"))?;
+        match inputs.iter_mut().find(|(n, _)| n == name) {
+            Some((_, old)) => *old = values,
+            None => inputs.push((name.to_string(), values)),
+        }
+        self.calculate_witness(inputs, sanity_check)
+    }
+
+    /// Calculates the witness of a circuit whose input signals are all single values, see
+    /// [`WitnessInputs::from_scalars`](super::WitnessInputs::from_scalars).
+    pub fn calculate_witness_scalars<S: Into<String>, I: IntoIterator<Item = (S, BigInt)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        self.calculate_witness(super::WitnessInputs::from_scalars(inputs), sanity_check)
+    }
 
-        let modulus = <E::ScalarField as PrimeField>::MODULUS;
+    /// Calculates the witness with every element reduced into `[0, prime)`, for field
+    /// arithmetic outside of arkworks.
+    pub fn calculate_witness_reduced<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigUint>> {
+        let witness = self.calculate_witness(inputs, sanity_check)?;
+        Ok(witness
+            .iter()
+            .map(|w| w.mod_floor(&self.prime).into_parts().1)
+            .collect())
+    }
 
-        // convert it to field elements
-        use num_traits::Signed;
-        let witness = witness
+    /// Returns the name of every input signal of `inputs` with the FNV-1a hash, split into
+    /// its most and least significant 32 bits, by which it is passed to `setInputSignal`.
+    ///
+    /// This helps debugging inputs which the circuit doesn't find: signals are hashed by
+    /// their name within the main component, e.g. `a` and not `main.a` as in the `.sym` file.
+    pub fn input_hashes<S: AsRef<str>, V, I: IntoIterator<Item = (S, V)>>(
+        inputs: I,
+    ) -> Vec<(String, u32, u32)> {
+        inputs
             .into_iter()
-            .map(|w| {
-                let w = if w.sign() == num_bigint::Sign::Minus {
-                    // Need to negate the witness element if negative
-                    modulus.into() - w.abs().to_biguint().unwrap()
-                } else {
-                    w.to_biguint().unwrap()
-                };
-                E::ScalarField::from(w)
+            .map(|(name, _)| {
+                let (msb, lsb) = fnv(name.as_ref());
+                (name.as_ref().to_string(), msb, lsb)
             })
-            .collect::<Vec<_>>();
+            .collect()
+    }
 
-        Ok(witness)
+    /// Returns the elements of `witness` within `2^32` below the prime along with their
+    /// index, as the negative value they most likely stand for, e.g. `-1` for `prime - 1`.
+    /// For primes below `2^33`, only the elements in the upper half of the field are returned.
+    ///
+    /// Such elements usually come from a subtraction which underflowed in the circuit, and
+    /// are easily mishandled by tools which don't reduce them, so this helps circuit authors
+    /// spot unintended underflows.
+    pub fn likely_negative_elements(&self, witness: &[BigInt]) -> Vec<(usize, BigInt)> {
+        let threshold = (&self.prime - BigInt::from(1u64 << 32)).max(&self.prime / 2 + 1);
+        witness
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| **w >= threshold && **w < self.prime)
+            .map(|(i, w)| (i, w - &self.prime))
+            .collect()
     }
-}
 
-// callback hooks for debugging
-wasm3::make_func_wrapper!(
-    exception_handler_wrap: exception_handler(_arg: i32) -> ()
-);
-fn exception_handler(_arg: i32) {
-    // Implementation for runtime.exceptionHandler
-    // You can handle exceptions here
-}
-wasm3::make_func_wrapper!(
-    print_error_message_wrap: print_error_message() -> ()
-);
-fn print_error_message() {
-    // Implementation for runtime.printErrorMessage
-    println!("Error message printed from Rust");
-}
-wasm3::make_func_wrapper!(
-    write_buffer_message_wrap: write_buffer_message() -> ()
-);
-fn write_buffer_message() {
-    // Implementation for runtime.writeBufferMessage
-    println!("Buffer message written from Rust");
+    /// Calculates the witness for input signals given as arkworks field elements, which are
+    /// passed to the circuit in their canonical form.
+    ///
+    /// Returns an error if the modulus of `F` is not the circuit's prime.
+    #[cfg(feature = "arkworks")]
+    pub fn calculate_witness_from_field<
+        F: ark_ff::PrimeField,
+        I: IntoIterator<Item = (String, Vec<F>)>,
+    >(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        self.check_modulus::<F>()?;
+
+        let inputs = inputs.into_iter().map(|(name, values)| {
+            let values = values
+                .into_iter()
+                .map(|v| BigInt::from(Into::<BigUint>::into(v)))
+                .collect();
+            (name, values)
+        });
+        self.calculate_witness(inputs, sanity_check)
+    }
+
+    /// Calculates the witness but only reads back the circuit's public outputs, i.e. the
+    /// witness elements `1..=n` for the `n` outputs of [`Self::num_outputs`].
+    pub fn calculate_outputs<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        let num_outputs = self.num_outputs()?;
+        let options = self.options(sanity_check);
+        self.with_wasm(|instance| {
+            let mut scratch = Vec::new();
+            write_inputs(instance, inputs, options, &mut scratch)?;
+            let witness_size = witness_size(instance, options)?;
+            if num_outputs >= witness_size as usize {
+                return Err(eyre!(
+                    "Can't read {} outputs from a witness of {} elements",
+                    num_outputs,
+                    witness_size
+                ));
+            }
+
+            let mut outputs = Vec::with_capacity(num_outputs);
+            for i in 1..=num_outputs as u32 {
+                outputs.push(read_witness_element(instance, i, &mut scratch)?);
+            }
+            check_exception(instance, options)?;
+            Ok(outputs)
+        })
+    }
+
+    /// Calculates the witness and returns its elements as little-endian bytes, each padded to
+    /// `n64 * 8` bytes, i.e. the contents of the witness section of a `.wtns` file. This skips
+    /// the conversion of every element to a `BigInt`.
+    pub fn calculate_witness_raw_bytes<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<u8>> {
+        let options = self.options(sanity_check);
+        let n8 = self.n64 as usize * 8;
+        self.with_wasm(|instance| {
+            let mut words = Vec::new();
+            write_inputs(instance, inputs, options, &mut words)?;
+            let witness_size = witness_size(instance, options)? as usize;
+
+            let mut bytes = Vec::with_capacity(witness_size * n8);
+            for i in 0..witness_size {
+                instance.get_witness(i as u32)?;
+                instance.read_shared_rw_memory_bulk(&mut words)?;
+                let start = bytes.len();
+                for word in &words {
+                    bytes.extend_from_slice(&word.to_le_bytes());
+                }
+                bytes.resize(start + n8, 0);
+            }
+            check_exception(instance, options)?;
+
+            Ok(bytes)
+        })
+    }
+
+    /// Calculates the witness and writes it to a `.wtns` file at `path` through a memory
+    /// map, as the elements are read from the circuit. Unlike [`Self::write_wtns_file`],
+    /// the witness is never held in memory, which allows calculating witnesses larger than
+    /// the available RAM.
+    #[cfg(feature = "memmap")]
+    pub fn calculate_witness_mmap<
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+        P: AsRef<std::path::Path>,
+    >(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+        path: P,
+    ) -> Result<()> {
+        let options = self.options(sanity_check);
+        let n8 = self.n64 as usize * 8;
+        self.with_wasm(|instance| {
+            let mut words = Vec::new();
+            write_inputs(instance, inputs, options, &mut words)?;
+            let witness_size = witness_size(instance, options)?;
+            let mut header = Vec::new();
+            super::wtns::write_wtns_header(&mut header, &self.prime, self.n64, witness_size)?;
+
+            let file = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path.as_ref())
+                .map_err(CircomError::Io)?;
+            file.set_len((header.len() + n8 * witness_size as usize) as u64)
+                .map_err(CircomError::Io)?;
+            // SAFETY: the file was just truncated and is only modified through the map while
+            // it is alive
+            let mut mmap = unsafe { memmap2::MmapMut::map_mut(&file) }.map_err(CircomError::Io)?;
+
+            mmap[..header.len()].copy_from_slice(&header);
+            for (i, element) in mmap[header.len()..].chunks_exact_mut(n8).enumerate() {
+                instance.get_witness(i as u32)?;
+                instance.read_shared_rw_memory_bulk(&mut words)?;
+                for (bytes, word) in element.chunks_exact_mut(4).zip(&words) {
+                    bytes.copy_from_slice(&word.to_le_bytes());
+                }
+            }
+            check_exception(instance, options)?;
+
+            mmap.flush().map_err(CircomError::Io)?;
+            Ok(())
+        })
+    }
+
+    /// Calculates the witness and labels its elements with the signal names of the circuit's
+    /// `.sym` file at `sym_path`, in the order of the file. Signals which were optimized out
+    /// are skipped.
+    pub fn calculate_witness_named<
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+        P: AsRef<std::path::Path>,
+    >(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+        sym_path: P,
+    ) -> Result<Vec<(String, BigInt)>> {
+        let symbols = super::sym::read_sym_file(sym_path.as_ref())?;
+        let witness = self.calculate_witness(inputs, sanity_check)?;
+        super::sym::label_witness(&symbols, &witness)
+    }
+
+    /// Sets the symbol table [`Self::witness_for_signal`] looks signals up in, e.g. as read
+    /// with [`read_sym`](super::read_sym). Clones of the calculator share it.
+    ///
+    /// This also reads the [`Self::input_schema`] for the symbols from the circuit, loading it
+    /// if needed, and returns an error if that fails.
+    pub fn set_symbols(&mut self, symbols: Vec<Symbol>) -> Result<()> {
+        let schema = match self.with_wasm(|instance| read_input_schema(instance, &symbols)) {
+            Ok(schema) => Some(schema.into()),
+            Err(e) if matches!(CircomError::of(&e), Some(CircomError::MissingExport(_))) => None,
+            Err(e) => return Err(e),
+        };
+        self.symbols = Some(symbols.into());
+        self.input_schema = schema;
+        Ok(())
+    }
+
+    /// Reads the circuit's `.sym` file at `sym_path` as the symbol table, see
+    /// [`Self::set_symbols`].
+    pub fn load_symbols(&mut self, sym_path: impl AsRef<std::path::Path>) -> Result<()> {
+        let symbols = super::sym::read_sym_file(sym_path.as_ref())?;
+        self.set_symbols(symbols)
+    }
+
+    /// Returns the number of the circuit's public outputs, which circom places at the start of
+    /// the witness, before the inputs. The wasm doesn't know it, so it's derived from where the
+    /// first input of [`Self::input_schema`] is in the symbol table.
+    ///
+    /// Returns an error without a symbol table, or if no input signal is in it.
+    pub fn num_outputs(&self) -> Result<usize> {
+        let symbols = self.symbols.as_deref().ok_or_else(|| {
+            eyre!("The circuit's outputs can't be located without a symbol table")
+        })?;
+        let inputs = self.input_schema().iter().map(|(name, _)| name.as_str());
+        super::sym::num_outputs(symbols, inputs)
+            .ok_or_else(|| eyre!("None of the circuit's input signals is in the symbol table"))
+    }
+
+    /// Returns the symbol table, if one was set.
+    pub fn symbols(&self) -> Option<&[Symbol]> {
+        self.symbols.as_deref()
+    }
+
+    /// Returns the value of the signal `name`, e.g. `main.out`, in the witness computed by
+    /// the last calculation, looking up its witness index in the symbol table.
+    ///
+    /// Returns an error if no symbol table was set, if the signal isn't in it or was
+    /// optimized out, or if no witness was calculated, see [`Self::witness_element`].
+    pub fn witness_for_signal(&self, name: &str) -> Result<BigInt> {
+        let symbols = self
+            .symbols
+            .as_deref()
+            .ok_or_else(|| eyre!("No symbol table was set"))?;
+        let index = super::sym::witness_index(symbols, name)?;
+        self.witness_element(index)
+    }
+
+    /// Like [`Self::calculate_witness`], but first checks that every input signal of the
+    /// circuit receives exactly as many values as it is declared with, and that the total
+    /// number of values matches [`Self::input_size`]. Returns an error listing the
+    /// mismatched or unknown signals and the number of unset or excess values otherwise.
+    ///
+    /// This relies on the `getInputSignalSize` / `getInputSize` exports of circom >= 2.1;
+    /// checks whose export is missing are skipped. With a symbol table, the inputs are checked
+    /// against the cached [`Self::input_schema`] instead of asking the circuit every time.
+    pub fn calculate_witness_checked<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        let inputs = inputs.into_iter().collect::<Vec<_>>();
+        let options = self.options(sanity_check);
+        // circuits reporting their input size also report the sizes of their signals
+        let input_size = self.input_size()?;
+        let schema = match input_size {
+            Some(_) => self.input_schema.as_deref(),
+            None => None,
+        };
+        self.with_wasm(|instance| {
+            match &schema {
+                Some(schema) => check_input_sizes(&inputs, input_size, |name| {
+                    let size = schema
+                        .iter()
+                        .find(|(n, _)| n == name)
+                        .map(|(_, size)| *size);
+                    Ok(Some(size.unwrap_or(0) as u32))
+                })?,
+                None => validate_inputs(instance, &inputs)?,
+            }
+            run(instance, inputs, options)
+        })
+    }
+
+    /// Returns the input signals of the circuit's main component along with their number of
+    /// values, in the order of the symbol table (see [`Self::set_symbols`]), e.g. to build
+    /// inputs for a circuit or to validate them.
+    ///
+    /// The sizes come from the circuit's `getInputSignalSize` export, but the wasm doesn't
+    /// know the names of its signals, so they come from the symbol table: the schema is read
+    /// once, when the symbol table is set. It is empty without a symbol table, or if the
+    /// circuit doesn't report the sizes of its input signals, i.e. was built with
+    /// circom < 2.1.
+    pub fn input_schema(&self) -> &[(String, usize)] {
+        self.input_schema.as_deref().unwrap_or(&[])
+    }
+
+    /// Calculates the witness with input values computed on demand: the circuit's input
+    /// signals are discovered from the symbol table (see [`Self::set_symbols`]), and `inputs`
+    /// is called once with the name of each of them, in the order of the table. Signals for
+    /// which it returns `None` are left unset. Values are never requested for signals the
+    /// circuit doesn't declare as inputs, so costly ones need only be computed if used.
+    ///
+    /// Discovering the inputs relies on the `getInputSignalSize` export of circom >= 2.1.
+    pub fn calculate_witness_lazy<F: FnMut(&str) -> Option<Vec<BigInt>>>(
+        &self,
+        mut inputs: F,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        if self.symbols.is_none() {
+            return Err(eyre!(
+                "The circuit's input signals can't be discovered without a symbol table"
+            ));
+        }
+        let schema = self
+            .input_schema
+            .as_deref()
+            .ok_or_else(|| CircomError::MissingExport("getInputSignalSize".to_string()))?;
+        let options = self.options(sanity_check);
+        self.with_wasm(|instance| {
+            let mut scratch = Vec::new();
+            let mut writer = InputWriter::new(instance, options, &mut scratch)?;
+            for (name, _) in schema.iter() {
+                if let Some(values) = inputs(name) {
+                    writer.write(name, &values)?;
+                }
+            }
+            read_witness(instance, options, &mut scratch, |_, _| {})
+        })
+    }
+
+    /// Calculates the witness like [`Self::calculate_witness`], but still reads the witness
+    /// when the circuit reports an exception, e.g. to see how far a failing circuit got. The
+    /// exception is returned along with the witness instead of failing the calculation, and
+    /// the inputs following the one whose signal raised it are left unset.
+    ///
+    /// Other errors, e.g. invalid inputs, are returned as usual. With
+    /// [`Self::set_exit_on_exception`] turned off, exceptions are never captured.
+    pub fn calculate_witness_partial<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<PartialWitness> {
+        let options = self.options(sanity_check);
+        self.with_wasm(|instance| {
+            let mut scratch = Vec::new();
+            let exception = match write_inputs(instance, inputs, options, &mut scratch) {
+                Ok(()) => None,
+                Err(e) if matches!(CircomError::of(&e), Some(CircomError::CircomException(_))) => {
+                    Some(e)
+                }
+                Err(e) => return Err(e),
+            };
+            let witness = match exception {
+                // read what the circuit got to without failing on the same exception again
+                Some(_) => {
+                    let lenient = RunOptions {
+                        exit_on_exception: false,
+                        ..options
+                    };
+                    read_witness(instance, lenient, &mut scratch, |_, _| {})?
+                }
+                None => read_witness(instance, options, &mut scratch, |_, _| {})?,
+            };
+            Ok(PartialWitness { witness, exception })
+        })
+    }
+
+    /// Calculates the witnesses for a batch of inputs on the loaded instance, re-running `init`
+    /// for every entry. Fails on the first entry which errors.
+    ///
+    /// Re-running `init` does not clear the circuit's signal memory, so every entry must
+    /// provide all of the circuit's input signals.
+    ///
+    /// The limb buffer input values and witness elements go through is allocated once for the
+    /// whole batch.
+    pub fn calculate_witnesses<I, B>(
+        &self,
+        batch: B,
+        sanity_check: bool,
+    ) -> Result<Vec<Vec<BigInt>>>
+    where
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+        B: IntoIterator<Item = I>,
+    {
+        let options = self.options(sanity_check);
+        self.with_wasm(|instance| {
+            let mut scratch = Vec::new();
+            batch
+                .into_iter()
+                .map(|inputs| run_with_scratch(instance, inputs, options, &mut scratch))
+                .collect()
+        })
+    }
+
+    /// Calculates the witnesses for a batch of `batch_size` entries whose inputs are laid out
+    /// column-wise, like [`Self::calculate_witnesses`] but without building the inputs of
+    /// every entry. Fails on the first entry which errors.
+    ///
+    /// Every column is an input signal along with its values for the whole batch, entry after
+    /// entry: the `n` values of a signal for entry `i` are `values[i * n..(i + 1) * n]`, so its
+    /// column holds `batch_size * n` values. For instance, two entries of a circuit with the
+    /// inputs `a` and `b[2]` are laid out as
+    ///
+    /// ```text
+    /// [("a", [a0, a1]), ("b", [b0[0], b0[1], b1[0], b1[1]])]
+    /// ```
+    ///
+    /// Returns an error before running any entry if the length of a column is not a multiple
+    /// of `batch_size`.
+    pub fn calculate_witnesses_columnar(
+        &self,
+        columns: &[(String, Vec<BigInt>)],
+        batch_size: usize,
+        sanity_check: bool,
+    ) -> Result<Vec<Vec<BigInt>>> {
+        let mut sizes = Vec::with_capacity(columns.len());
+        for (name, values) in columns {
+            let size = match values.len().checked_rem(batch_size) {
+                Some(0) => values.len() / batch_size,
+                // an empty batch may only have empty columns
+                None if values.is_empty() => 0,
+                _ => {
+                    return Err(eyre!(
+                        "The column of {} has {} values, not a multiple of the batch size {}",
+                        name,
+                        values.len(),
+                        batch_size
+                    ))
+                }
+            };
+            sizes.push(size);
+        }
+
+        let options = self.options(sanity_check);
+        self.with_wasm(|instance| {
+            let mut scratch = Vec::new();
+            let mut witnesses = Vec::with_capacity(batch_size);
+            for entry in 0..batch_size {
+                let mut writer = InputWriter::new(instance, options, &mut scratch)?;
+                for ((name, values), n) in columns.iter().zip(&sizes) {
+                    writer.write(name, &values[entry * n..(entry + 1) * n])?;
+                }
+                witnesses.push(read_witness(instance, options, &mut scratch, |_, _| {})?);
+            }
+            Ok(witnesses)
+        })
+    }
+
+    /// Like [`Self::calculate_witnesses`], but keeps going when an entry fails and returns
+    /// the result of every entry. The outer error is only returned if the circuit could not
+    /// be loaded at all.
+    pub fn calculate_witnesses_collect<I, B>(
+        &self,
+        batch: B,
+        sanity_check: bool,
+    ) -> Result<Vec<Result<Vec<BigInt>, WitnessError>>>
+    where
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+        B: IntoIterator<Item = I>,
+    {
+        let options = self.options(sanity_check);
+        self.with_wasm(|instance| {
+            let mut scratch = Vec::new();
+            Ok(batch
+                .into_iter()
+                .map(|inputs| run_with_scratch(instance, inputs, options, &mut scratch))
+                .collect())
+        })
+    }
+
+    /// Calculates the witness for `inputs` with this calculator, then with `other`, then with
+    /// this calculator again on its reused instance, and panics unless all three witnesses
+    /// are identical. Returns the witness.
+    ///
+    /// Meant for tests of code which reuses or clones calculators, where an instance left in
+    /// a stale state would make witnesses diverge: `other` is typically constructed
+    /// separately, e.g. with [`Self::from_bytes`] or as a clone, which gets its own instance.
+    ///
+    /// # Panics
+    ///
+    /// If a calculation fails or the witnesses differ, naming the first differing element.
+    #[cfg(feature = "test-util")]
+    pub fn assert_same_witness(
+        &self,
+        other: &WitnessCalculator,
+        inputs: &[(String, Vec<BigInt>)],
+        sanity_check: bool,
+    ) -> Vec<BigInt> {
+        let calculate = |wtns: &WitnessCalculator, label: &str| {
+            wtns.calculate_witness(inputs.to_vec(), sanity_check)
+                .unwrap_or_else(|e| panic!("The {} calculation failed: {:?}", label, e))
+        };
+        let witness = calculate(self, "first");
+        for (label, wtns) in [("other calculator's", other), ("repeated", self)] {
+            let other_witness = calculate(wtns, label);
+            if let Some(i) = (0..witness.len().max(other_witness.len()))
+                .find(|&i| witness.get(i) != other_witness.get(i))
+            {
+                panic!(
+                    "The {} witness differs at element {}: {:?} instead of {:?}",
+                    label,
+                    i,
+                    other_witness.get(i),
+                    witness.get(i)
+                );
+            }
+        }
+        witness
+    }
+
+    /// Validates `inputs` without extracting the witness: runs `init`, writes every input and
+    /// returns an error if the circuit raised an exception while doing so.
+    pub fn check_inputs<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<()> {
+        let options = self.options(sanity_check);
+        self.with_wasm(|instance| write_inputs(instance, inputs, options, &mut Vec::new()))
+    }
+
+    /// Sets whether an exception reported by the circuit ends the calculation with an
+    /// [`ExitCode`] error (the default), independently of the sanity check flag. When
+    /// disabled, exceptions are ignored and whatever the circuit computed is returned, which
+    /// is only useful for debugging circuits.
+    pub fn set_exit_on_exception(&mut self, exit: bool) {
+        self.exit_on_exception = exit;
+    }
+
+    /// Enables or disables (the default) reproducible mode, for audits which need identical
+    /// behavior across machines. The circuit is then always run in a runtime of
+    /// [`Self::REPRODUCIBLE_RUNTIME_SIZE`] bytes, and loading fails unless the linked imports
+    /// are exactly circom's `runtime` functions in their canonical order, which rules out
+    /// handlers added with [`Self::register_import`] for other modules.
+    ///
+    /// Changing the mode drops the loaded instance.
+    pub fn set_reproducible(&mut self, reproducible: bool) {
+        if reproducible != self.reproducible {
+            self.reproducible = reproducible;
+            self.reset();
+        }
+    }
+
+    fn options(&self, sanity_check: bool) -> RunOptions<'_> {
+        RunOptions {
+            prime: &self.prime,
+            limits: self.limits,
+            sanity_check,
+            exit_on_exception: self.exit_on_exception,
+            messages: self.messages.as_ref(),
+        }
+    }
+
+    /// Re-runs the circuit's `init` on the loaded instance with a new sanity check flag.
+    ///
+    /// Every calculation already calls `init` with the flag it is given, so this is only needed
+    /// to prepare the instance ahead of time. Note that `init` resets the circuit's input
+    /// counters but not its signal memory: values of input signals set by an earlier
+    /// calculation remain in place until overwritten, so a calculation must always provide all
+    /// of the circuit's inputs. Use [`Self::reset`] to start from a pristine instance.
+    pub fn reinit(&self, sanity_check: bool) -> Result<()> {
+        self.with_wasm(|instance| instance.init(sanity_check))
+    }
+
+    // Calculates the witness on the instance prepared by `reinit`, see `run_initialized`. The
+    // instance is initialized first if anything used it since its last `init`, e.g. a
+    // calculation through `PreparedCircuit::calculator` or a `reset`.
+    pub(crate) fn calculate_prepared<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        let options = self.options(sanity_check);
+        self.with_wasm(|instance| {
+            if !instance.is_fresh() {
+                instance.init(sanity_check)?;
+            }
+            run_initialized(instance, inputs, options)
+        })
+    }
+
+    /// Drops the loaded instance, so that the next calculation starts from a freshly loaded
+    /// module with all signal and shared memory cleared.
+    pub fn reset(&self) {
+        if let Some(slot) = self.instance.find() {
+            *slot.borrow_mut() = None;
+        }
+    }
+
+    /// Runs `f` with the wasm3 runtime of the loaded circuit, loading it on first use, e.g. to
+    /// call circuit-specific exports the crate doesn't cover through
+    /// [`Runtime::find_function`].
+    ///
+    /// This is an escape hatch: the runtime is the one calculations run on, and `f` is
+    /// responsible for keeping up circom's protocol. Calls made through it are not tracked,
+    /// so e.g. an `init` doesn't count for [`Self::witness_element`], and whatever `f` leaves
+    /// in the signal or shared memory is seen by the next calculation. Use [`Self::reset`]
+    /// afterwards to start over from a freshly loaded module. The [`Self::call_stats`] of the
+    /// last calculation are kept.
+    ///
+    /// The runtime is exposed rather than the circuit's [`Module`]: wasm3 only hands out a
+    /// module while loading it, borrowing the runtime it's loaded in, so the calculator can't
+    /// keep it around. The circuit is the only module of the runtime, so
+    /// [`Runtime::find_function`] finds exactly the module's exports.
+    pub fn with_runtime<T>(&self, f: impl FnOnce(&Runtime) -> Result<T>) -> Result<T> {
+        let slot = self.instance.slot();
+        let mut cache = borrow_slot_mut(&slot)?;
+        let instance = self.loaded_instance(&mut cache)?;
+        let result = f(instance.runtime());
+        // `f` may have set inputs, so the next calculation can't skip `init`
+        if instance.state.get() != InitState::Uninitialized {
+            instance.state.set(InitState::Read);
+        }
+        result
+    }
+
+    // Runs `f` on the loaded instance, loading the circuit on first use
+    fn with_wasm<T>(&self, f: impl FnOnce(&Wasm) -> Result<T>) -> Result<T> {
+        let slot = self.instance.slot();
+        let mut cache = borrow_slot_mut(&slot)?;
+        let instance = self.loaded_instance(&mut cache)?;
+        #[cfg(feature = "call-stats")]
+        instance.stats.take();
+        f(&instance.wasm(self.direct_memory))
+    }
+
+    // Returns the instance in `cache`, loading the circuit into it first if it's empty
+    fn loaded_instance<'c>(&self, cache: &'c mut Option<Instance>) -> Result<&'c Instance> {
+        let instance = match cache.take() {
+            Some(instance) => instance,
+            None => self.load_instance()?,
+        };
+        Ok(cache.insert(instance))
+    }
+
+    fn load_instance(&self) -> Result<Instance> {
+        let instance = Instance::load(
+            &self.data,
+            &self.imports,
+            self.runtime_module.as_deref(),
+            Self::REPRODUCIBLE_RUNTIME_SIZE,
+            self.reproducible,
+        )?;
+        if self.metadata.get().is_none() {
+            self.check_supplied_prime(&instance.wasm(self.direct_memory))?;
+        }
+        Ok(instance)
+    }
+
+    /// Like [`Self::calculate_witness`], but gives up with a [`CircomError::Timeout`] if the
+    /// calculation doesn't finish within `timeout`, e.g. to protect a server from circuits or
+    /// inputs which take pathologically long.
+    ///
+    /// wasm3 can't interrupt a running call, so the calculation runs on a worker thread of
+    /// the calculator, on a clone of it which loads its own instance there. The thread is
+    /// reused by later calls. On timeout, the calculation is left to run until the circuit
+    /// returns, which might be never, while the calculator and its instance are left as they
+    /// were. Until it returns, the calls of this method fail right away, so that a
+    /// calculator leaves at most one stuck thread behind; a clone of the calculator has its
+    /// own worker thread.
+    pub fn calculate_witness_with_timeout<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+        timeout: std::time::Duration,
+    ) -> Result<Vec<BigInt>> {
+        let inputs = inputs.into_iter().collect::<Vec<_>>();
+        let mut wtns = self.clone();
+        *wtns.last_inputs.get_mut() = None;
+        let (sender, receiver) = mpsc::channel();
+        self.timeout_worker
+            .run((wtns, inputs, sanity_check, sender), timeout)?;
+
+        let (witness, last_inputs) = match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                return Err(CircomError::Timeout(timeout).into())
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                self.timeout_worker.reset();
+                return Err(eyre!("The calculation panicked"));
+            }
+        };
+        let witness = witness?;
+        if last_inputs.is_some() {
+            *self.last_inputs.borrow_mut() = last_inputs;
+        }
+        Ok(witness)
+    }
+
+    // Checks the prime given to `from_bytes_with_prime` against the one of the circuit, and
+    // reads the metadata which was skipped along with it
+    fn check_supplied_prime(&self, instance: &Wasm) -> Result<()> {
+        let prime = instance.prime()?;
+        if prime != self.prime {
+            return Err(CircomError::Load(format!(
+                "Circuit prime {} does not match the supplied prime {}",
+                prime, self.prime
+            ))
+            .into());
+        }
+        if let Some(n32) = self.n32 {
+            let actual = instance.get_field_num_len32()?;
+            if actual != n32 {
+                return Err(CircomError::Load(format!(
+                    "Circuit field elements of {} words do not match the supplied {}",
+                    actual, n32
+                ))
+                .into());
+            }
+        }
+        self.metadata.set(Some(Metadata::read(instance)?));
+        Ok(())
+    }
+
+    /// Returns the number of calls made to each of the circuit's exports by the last
+    /// calculation (or batch of calculations), e.g. to see how much time is spent crossing
+    /// into wasm.
+    #[cfg(feature = "call-stats")]
+    pub fn call_stats(&self) -> Result<CallStats> {
+        let slot = match self.instance.find() {
+            Some(slot) => slot,
+            None => return Ok(CallStats::default()),
+        };
+        let cache = borrow_slot(&slot)?;
+        Ok(cache
+            .as_ref()
+            .map(|instance| instance.stats.borrow().clone())
+            .unwrap_or_default())
+    }
+
+    /// Returns the size in bytes of the circuit's linear memory after the last calculation,
+    /// or `None` if no calculation ran yet (or since [`Self::reset`]). Wasm memory only ever
+    /// grows, so this is the peak memory the circuit used over all calculations since it was
+    /// loaded, e.g. to size the memory of the host or of a sandbox running it.
+    ///
+    /// The linear memory is allocated on top of the wasm3 runtime's fixed-size stack of
+    /// [`Self::REPRODUCIBLE_RUNTIME_SIZE`] bytes.
+    pub fn memory_size(&self) -> Result<Option<usize>> {
+        let slot = match self.instance.find() {
+            Some(slot) => slot,
+            None => return Ok(None),
+        };
+        let cache = borrow_slot(&slot)?;
+        Ok(cache.as_ref().map(Instance::memory_size))
+    }
+
+    /// Reads the element at `index` of the witness computed by the last calculation, without
+    /// recomputing the whole witness.
+    ///
+    /// Returns an error if no circuit instance is loaded, e.g. before the first calculation or
+    /// after [`Self::reset`], or if `index` is out of bounds.
+    pub fn witness_element(&self, index: u32) -> Result<BigInt> {
+        let slot = self.instance.slot();
+        let cache = borrow_slot(&slot)?;
+        let instance = cache
+            .as_ref()
+            .ok_or_else(|| eyre!("No witness has been calculated yet"))?
+            .wasm(self.direct_memory);
+        let n32 = check_n32(instance.get_field_num_len32()?)?;
+        let witness_size = instance.get_witness_size()?;
+        if index >= witness_size {
+            return Err(eyre!(
+                "Witness index {} is out of bounds for a witness of {} elements",
+                index,
+                witness_size
+            ));
+        }
+        read_witness_element(&instance, index, &mut vec![0; n32 as usize])
+    }
+
+    #[cfg(feature = "arkworks")]
+    pub fn calculate_witness_element<
+        E: ark_ec::pairing::Pairing,
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+    >(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<E::ScalarField>> {
+        let witness = self.calculate_witness(inputs, sanity_check)?;
+        to_field_elements(&witness)
+    }
+
+    /// Calculates the witness as elements of the field `F`, which doesn't need to be the
+    /// scalar field of a pairing, e.g. Goldilocks for circuits compiled with
+    /// `circom --prime goldilocks`.
+    ///
+    /// Returns an error if the modulus of `F` is not the circuit's prime.
+    #[cfg(feature = "arkworks")]
+    pub fn calculate_witness_field<
+        F: ark_ff::PrimeField,
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+    >(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<F>> {
+        self.check_modulus::<F>()?;
+        let witness = self.calculate_witness(inputs, sanity_check)?;
+        to_field_elements(&witness)
+    }
+
+    // Checks that `F` is the circuit's field
+    #[cfg(feature = "arkworks")]
+    fn check_modulus<F: ark_ff::PrimeField>(&self) -> Result<()> {
+        let modulus: BigUint = F::MODULUS.into();
+        let modulus = BigInt::from(modulus);
+        if modulus != self.prime {
+            return Err(eyre!(
+                "Field modulus {} does not match the circuit's prime {}",
+                modulus,
+                self.prime
+            ));
+        }
+        Ok(())
+    }
+
+    /// Calculates the witness as scalars of `E` and writes them to `writer` in arkworks'
+    /// compressed `CanonicalSerialize` format of a `Vec<E::ScalarField>`, to read back with
+    /// `CanonicalDeserialize`. Use [`Self::write_wtns`] for snarkjs' `.wtns` format instead.
+    ///
+    /// Returns an error if the scalar field of `E` is not the circuit's field.
+    #[cfg(feature = "arkworks")]
+    pub fn calculate_witness_canonical<
+        E: ark_ec::pairing::Pairing,
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+        W: std::io::Write,
+    >(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+        writer: W,
+    ) -> Result<()> {
+        use ark_serialize::CanonicalSerialize;
+
+        self.check_modulus::<E::ScalarField>()?;
+        let witness = self.calculate_witness(inputs, sanity_check)?;
+        to_field_elements::<E::ScalarField>(&witness)?
+            .serialize_compressed(writer)
+            .map_err(|e| eyre!("Failed to serialize the witness: {}", e))
+    }
+
+    /// Calculates the witness once and returns it both as integers and as field elements,
+    /// e.g. to prove with the latter while logging the former.
+    #[cfg(feature = "arkworks")]
+    pub fn calculate_witness_with_elements<
+        E: ark_ec::pairing::Pairing,
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+    >(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<(Vec<BigInt>, Vec<E::ScalarField>)> {
+        let witness = self.calculate_witness(inputs, sanity_check)?;
+        let elements = to_field_elements(&witness)?;
+        Ok((witness, elements))
+    }
+
+    /// Checks `witness` against the constraints of the circuit's `.r1cs` file at `r1cs_path`,
+    /// over the scalar field of `E`, without running a prover. Returns the index of the
+    /// first constraint it doesn't satisfy, or `None` if it is a valid witness.
+    #[cfg(feature = "r1cs")]
+    pub fn check_witness_r1cs<E: ark_ec::pairing::Pairing>(
+        &self,
+        r1cs_path: impl AsRef<std::path::Path>,
+        witness: &[BigInt],
+    ) -> Result<Option<usize>> {
+        let file = std::fs::File::open(r1cs_path).map_err(CircomError::Io)?;
+        let r1cs = crate::circom::R1CSFile::<E>::new(std::io::BufReader::new(file))
+            .map_err(|e| CircomError::Load(format!("Invalid .r1cs file: {}", e)))?;
+        let elements = to_field_elements::<E::ScalarField>(witness)?;
+        R1CS::from(r1cs).first_unsatisfied(&elements)
+    }
+
+    /// Calculates the witness and arranges it as the variable assignment of an arkworks
+    /// `ConstraintSystem` for `r1cs`: the instance (the constant one followed by the public
+    /// signals) and the auxiliary witness, applying the R1CS wire mapping if there is one.
+    #[cfg(feature = "groth16")]
+    pub fn calculate_groth16_assignment<
+        E: ark_ec::pairing::Pairing,
+        I: IntoIterator<Item = (String, Vec<BigInt>)>,
+    >(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+        r1cs: &R1CS<E>,
+    ) -> Result<Groth16Assignment<E::ScalarField>> {
+        let witness = self.calculate_witness_element::<E, _>(inputs, sanity_check)?;
+        let wire = |i: usize| {
+            let index = match &r1cs.wire_mapping {
+                Some(m) => m[i],
+                None => i,
+            };
+            witness.get(index).copied().ok_or_else(|| {
+                eyre!(
+                    "R1CS wire {} maps to witness index {} but the witness has {} elements",
+                    i,
+                    index,
+                    witness.len()
+                )
+            })
+        };
+
+        Ok(Groth16Assignment {
+            instance: (0..r1cs.num_inputs).map(&wire).collect::<Result<_>>()?,
+            witness: (r1cs.num_inputs..r1cs.num_variables)
+                .map(&wire)
+                .collect::<Result<_>>()?,
+        })
+    }
 }
-wasm3::make_func_wrapper!(
-    show_shared_rw_memory_wrap: show_shared_rw_memory() -> ()
-);
-fn show_shared_rw_memory() {
-    // Implementation for runtime.showSharedRWMemory
-    println!("Shared read-write memory shown from Rust");
+
+/// A witness arranged as the variable assignment of an arkworks `ConstraintSystem`.
+#[cfg(feature = "groth16")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Groth16Assignment<F> {
+    /// The constant one followed by the public signals.
+    pub instance: Vec<F>,
+    /// The auxiliary (private) signals.
+    pub witness: Vec<F>,
+}
+
+#[cfg(feature = "groth16")]
+impl<F: Clone> Groth16Assignment<F> {
+    /// Returns the public signals, without the leading constant one, as passed to
+    /// `Groth16::verify`.
+    pub fn public_inputs(&self) -> &[F] {
+        &self.instance[1..]
+    }
+
+    /// Returns the instance followed by the witness, as passed to
+    /// `Groth16::create_proof_with_reduction_and_matrices`.
+    pub fn full_assignment(&self) -> Vec<F> {
+        let mut full = self.instance.clone();
+        full.extend_from_slice(&self.witness);
+        full
+    }
+}
+
+/// Calculates the witness for the given named input signals on a circuit loaded by another
+/// wasm engine, see [`CircomRuntime`].
+///
+/// Exceptions reported through [`report_exception`] end the calculation with an
+/// [`ExitCode`], and the default [`Limits`] apply.
+pub fn calculate_witness_on<R: CircomRuntime, I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+    runtime: &R,
+    inputs: I,
+    sanity_check: bool,
+) -> Result<Vec<BigInt>> {
+    let prime = runtime.prime()?;
+    let options = RunOptions {
+        prime: &prime,
+        limits: Limits::default(),
+        sanity_check,
+        exit_on_exception: true,
+        messages: None,
+    };
+    run(runtime, inputs, options)
+}
+
+/// Records an exception code the circuit passed to its `runtime.exceptionHandler` import,
+/// for circuits run by another wasm engine. The calculation running on the current thread
+/// fails with `ExitCode(code)` at its next check.
+pub fn report_exception(code: i32) {
+    exception_handler(code);
+}
+
+// Initializes the instance, writes the inputs and reads back the witness
+fn run<C: CircomBase + Circom2, I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+    instance: &C,
+    inputs: I,
+    options: RunOptions<'_>,
+) -> Result<Vec<BigInt>> {
+    run_with_progress(instance, inputs, options, |_, _| {})
+}
+
+// Like `run`, on an instance `init` already ran on, which runs `init` again afterwards, even
+// if the calculation fails, so that the next calculation can skip it too
+fn run_initialized<C: CircomBase + Circom2, I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+    instance: &C,
+    inputs: I,
+    options: RunOptions<'_>,
+) -> Result<Vec<BigInt>> {
+    let mut scratch = Vec::new();
+    let witness = (|| {
+        let mut writer = InputWriter::initialized(instance, options, &mut scratch)?;
+        for (name, values) in inputs {
+            writer.write(&name, &values)?;
+        }
+        read_witness(instance, options, &mut scratch, |_, _| {})
+    })();
+    instance.init(options.sanity_check)?;
+    witness
+}
+
+// Like `run`, reusing `scratch` as the limb buffer, e.g. across the entries of a batch. It
+// is resized to the circuit's n32 words, and every value written to it overwrites all of
+// them, so nothing carries over between calculations.
+fn run_with_scratch<C: CircomBase + Circom2, I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+    instance: &C,
+    inputs: I,
+    options: RunOptions<'_>,
+    scratch: &mut Vec<u32>,
+) -> Result<Vec<BigInt>> {
+    write_inputs(instance, inputs, options, scratch)?;
+    read_witness(instance, options, scratch, |_, _| {})
+}
+
+// Like `run`, calling `progress` with the number of elements read out of the witness size
+fn run_with_progress<C: CircomBase + Circom2, I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+    instance: &C,
+    inputs: I,
+    options: RunOptions<'_>,
+    progress: impl FnMut(usize, usize),
+) -> Result<Vec<BigInt>> {
+    // a single buffer for the limbs of every input value and witness element
+    let mut scratch = Vec::new();
+    write_inputs(instance, inputs, options, &mut scratch)?;
+    read_witness(instance, options, &mut scratch, progress)
+}
+
+// Reads the witness once the inputs are written, see `run_with_progress` for `progress`
+fn read_witness<C: CircomBase + Circom2>(
+    instance: &C,
+    options: RunOptions<'_>,
+    scratch: &mut [u32],
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Vec<BigInt>> {
+    let witness_size = witness_size(instance, options)? as usize;
+    let mut witness = Vec::with_capacity(witness_size);
+    let step = (witness_size / 100).max(1);
+    for i in 0..witness_size {
+        witness.push(read_witness_element(instance, i as u32, scratch)?);
+        if (i + 1) % step == 0 && i + 1 < witness_size {
+            progress(i + 1, witness_size);
+        }
+    }
+    check_exception(instance, options)?;
+    progress(witness_size, witness_size);
+
+    Ok(witness)
+}
+
+// Reads the witness element at `index` through the shared memory, using `limbs` (of the
+// circuit's n32 words) as the buffer
+fn read_witness_element<C: Circom2>(instance: &C, index: u32, limbs: &mut [u32]) -> Result<BigInt> {
+    instance.get_witness(index)?;
+    instance.read_shared_rw_memory_bulk(limbs)?;
+    Ok(BigInt::from_slice(Sign::Plus, limbs))
+}
+
+// Checks that all of the circuit's input values are provided, which trivially holds for
+// circuits without inputs
+fn validate_inputs<C: Circom2>(instance: &C, inputs: &[(String, Vec<BigInt>)]) -> Result<()> {
+    let input_size = instance.get_input_size()?;
+    check_input_sizes(inputs, input_size, |name| {
+        let (msb, lsb) = fnv(name);
+        instance.get_input_signal_size(msb, lsb)
+    })
+}
+
+// Checks the inputs against the circuit's `input_size` and the size of each signal returned
+// by `signal_size`, with the same meaning as `Circom2::get_input_signal_size`
+fn check_input_sizes(
+    inputs: &[(String, Vec<BigInt>)],
+    input_size: Option<u32>,
+    mut signal_size: impl FnMut(&str) -> Result<Option<u32>>,
+) -> Result<()> {
+    let mut problems = Vec::new();
+
+    let mut provided = 0;
+    let mut total = 0;
+    for (name, values) in inputs {
+        total += values.len();
+        match signal_size(name)? {
+            Some(0) => problems.push(format!("`{}` is not an input signal", name)),
+            Some(expected) => {
+                let expected = expected as usize;
+                if values.len() < expected {
+                    problems.push(format!(
+                        "`{}` has {} of its {} values",
+                        name,
+                        values.len(),
+                        expected
+                    ));
+                } else if values.len() > expected {
+                    problems.push(format!(
+                        "`{}` has {} values but is declared with {}",
+                        name,
+                        values.len(),
+                        expected
+                    ));
+                }
+                provided += values.len().min(expected);
+            }
+            None => provided += values.len(),
+        }
+    }
+
+    if let Some(expected) = input_size {
+        let expected = expected as usize;
+        if provided < expected {
+            problems.push(format!(
+                "{} of the circuit's {} input values are unset",
+                expected - provided,
+                expected
+            ));
+        }
+        if total > expected {
+            problems.push(format!(
+                "{} input values were provided but the circuit has {}",
+                total, expected
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(CircomError::Input(InputError::Mismatched(problems)).into())
+    }
+}
+
+// Returns the signals of the main component in `symbols` which the circuit declares as
+// inputs, along with their sizes
+fn read_input_schema<C: Circom2>(instance: &C, symbols: &[Symbol]) -> Result<Vec<(String, usize)>> {
+    let mut inputs = Vec::new();
+    for name in super::sym::main_signals(symbols) {
+        let (msb, lsb) = fnv(&name);
+        match instance.get_input_signal_size(msb, lsb)? {
+            Some(0) => {}
+            Some(size) => inputs.push((name, size as usize)),
+            None => return Err(CircomError::MissingExport("getInputSignalSize".to_string()).into()),
+        }
+    }
+    Ok(inputs)
+}
+
+// Initializes the instance and writes the inputs through `scratch`, which is left with the
+// circuit's n32 words to reuse as the limb buffer of the rest of the calculation
+fn write_inputs<C: CircomBase + Circom2, I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+    instance: &C,
+    inputs: I,
+    options: RunOptions<'_>,
+    scratch: &mut Vec<u32>,
+) -> Result<()> {
+    let mut writer = InputWriter::new(instance, options, scratch)?;
+    for (name, values) in inputs {
+        writer.write(&name, &values)?;
+    }
+    Ok(())
+}
+
+// Writes input signals to an initialized instance one at a time, as they become available
+struct InputWriter<'a, C> {
+    instance: &'a C,
+    options: RunOptions<'a>,
+    scratch: &'a mut Vec<u32>,
+    seen: HashSet<String>,
+    count: usize,
+}
+
+impl<'a, C: CircomBase + Circom2> InputWriter<'a, C> {
+    // Initializes the instance, see `write_inputs` for `scratch`
+    fn new(instance: &'a C, options: RunOptions<'a>, scratch: &'a mut Vec<u32>) -> Result<Self> {
+        instance.init(options.sanity_check)?;
+        Self::initialized(instance, options, scratch)
+    }
+
+    // Like `new`, for an instance `init` already ran on since its last calculation
+    fn initialized(
+        instance: &'a C,
+        options: RunOptions<'a>,
+        scratch: &'a mut Vec<u32>,
+    ) -> Result<Self> {
+        // drop any exception left over from a previous calculation on this thread
+        EXCEPTION.with(|e| e.set(None));
+        ERROR_MESSAGE.with(|m| m.set(false));
+        BUFFER_MESSAGE.with(|m| m.set(false));
+
+        let n32 = check_n32(instance.get_field_num_len32()?)?;
+        // a corrupt circuit could report field elements too small for its prime, which would
+        // silently truncate the inputs
+        if options.prime.bits() > 32 * n32 as u64 {
+            return Err(CircomError::Load(format!(
+                "Field elements of {} words can't hold the {}-bit prime",
+                n32,
+                options.prime.bits()
+            ))
+            .into());
+        }
+        scratch.resize(n32 as usize, 0);
+
+        Ok(Self {
+            instance,
+            options,
+            scratch,
+            seen: HashSet::new(),
+            count: 0,
+        })
+    }
+
+    fn write(&mut self, name: &str, values: &[BigInt]) -> Result<()> {
+        let options = self.options;
+        if !self.seen.insert(name.to_string()) {
+            return Err(CircomError::Input(InputError::Duplicate(name.to_string())).into());
+        }
+        self.count += values.len();
+        if self.count > options.limits.max_input_values {
+            let limit = options.limits.max_input_values;
+            return Err(CircomError::Input(InputError::TooManyValues(limit)).into());
+        }
+        let (msb, lsb) = fnv(name);
+
+        // the values are set at contiguous positions from 0, in order
+        for (i, value) in values.iter().enumerate() {
+            let position = u32::try_from(i).map_err(|_| {
+                eyre!(
+                    "Input signal {} has more values than circom can address",
+                    name
+                )
+            })?;
+            if !input_to_limbs(value, options.prime, self.scratch) {
+                return Err(CircomError::Input(InputError::OutOfRange {
+                    signal: format!("{}[{}]", name, i),
+                    bits: value.bits(),
+                    field_bits: options.prime.bits(),
+                })
+                .into());
+            }
+            self.instance.write_shared_rw_memory_bulk(self.scratch)?;
+            self.instance
+                .set_input_signal(msb, lsb, position)
+                .and_then(|_| check_exception(self.instance, options))
+                .wrap_err_with(|| format!("Failed to set input signal {}[{}]", name, i))?;
+        }
+        Ok(())
+    }
+}
+
+// Returns the circuit's witness size, checking it against the limit before anything is
+// allocated for the witness
+fn witness_size<C: Circom2>(instance: &C, options: RunOptions<'_>) -> Result<u32> {
+    let witness_size = instance.get_witness_size()?;
+    if witness_size > options.limits.max_witness_size {
+        return Err(eyre!(
+            "Witness size {} exceeds the limit of {}",
+            witness_size,
+            options.limits.max_witness_size
+        ));
+    }
+    Ok(witness_size)
+}
+
+// Writes an input value of `(-prime, prime)` to `limbs` in the circuit's representation,
+// mapping negative values to `prime + value` like circom's JS witness calculator. Returns
+// `false` for values out of that range.
+fn input_to_limbs(value: &BigInt, prime: &BigInt, limbs: &mut [u32]) -> bool {
+    if value.magnitude() >= prime.magnitude() {
+        return false;
+    }
+    if value.sign() == Sign::Minus {
+        write_limbs_le(&(prime + value), limbs);
+    } else {
+        write_limbs_le(value, limbs);
+    }
+    true
+}
+
+// Ends the calculation with an `ExitCode` if the circuit reported an exception since the
+// last check, unless exceptions are configured to be ignored. The error message the circuit
+// printed along with it, e.g. the template and line of a failed assert, is attached to the
+// error. Other messages only go to the message handler, if any.
+fn check_exception<C: CircomBase>(instance: &C, options: RunOptions<'_>) -> Result<()> {
+    // the buffer only holds the latest message, so output of the circuit's `log` gives way
+    // to an error message printed after it
+    if BUFFER_MESSAGE.with(|m| m.take()) && !ERROR_MESSAGE.with(|m| m.get()) {
+        if let Some(messages) = options.messages {
+            if let Some(message) = read_message(instance) {
+                (messages.0)(&message);
+            }
+        }
+    }
+    let message = if ERROR_MESSAGE.with(|m| m.take()) {
+        read_message(instance)
+    } else {
+        None
+    };
+    match EXCEPTION.with(|e| e.take()) {
+        Some(code) if options.exit_on_exception => {
+            let err = Report::from(CircomError::CircomException(ExitCode(code as u32)));
+            Err(match message {
+                Some(message) => err.wrap_err(message),
+                None => err,
+            })
+        }
+        _ => {
+            if let (Some(messages), Some(message)) = (options.messages, message) {
+                (messages.0)(&message);
+            }
+            Ok(())
+        }
+    }
+}
+
+// Reads the message in the circuit's message buffer, `None` if there is none
+fn read_message<C: CircomBase>(instance: &C) -> Option<String> {
+    let message = instance.read_message_buffer();
+    Some(message).filter(|message| !message.is_empty())
+}
+
+/// The host functions circom's witness generator imports from the `runtime` module.
+fn runtime_imports() -> ImportResolver {
+    let mut imports = ImportResolver::default();
+    imports.register(RUNTIME_MODULE, "exceptionHandler", |module, m, n| {
+        module.link_function::<i32, ()>(m, n, exception_handler_wrap)
+    });
+    imports.register(RUNTIME_MODULE, "printErrorMessage", |module, m, n| {
+        module.link_function::<(), ()>(m, n, print_error_message_wrap)
+    });
+    imports.register(RUNTIME_MODULE, "writeBufferMessage", |module, m, n| {
+        module.link_function::<(), ()>(m, n, write_buffer_message_wrap)
+    });
+    imports.register(RUNTIME_MODULE, "showSharedRWMemory", |module, m, n| {
+        module.link_function::<(), ()>(m, n, show_shared_rw_memory_wrap)
+    });
+    imports
+}
+
+thread_local! {
+    // code passed to `runtime.exceptionHandler` by the circuit running on this thread
+    static EXCEPTION: Cell<Option<i32>> = Cell::new(None);
+    // whether that circuit called `runtime.printErrorMessage` since the last check
+    static ERROR_MESSAGE: Cell<bool> = Cell::new(false);
+    // whether that circuit called `runtime.writeBufferMessage`, i.e. logged, since then
+    static BUFFER_MESSAGE: Cell<bool> = Cell::new(false);
+}
+
+// callback hooks for debugging
+wasm3::make_func_wrapper!(
+    exception_handler_wrap: exception_handler(_arg: i32) -> ()
+);
+fn exception_handler(arg: i32) {
+    // Implementation for runtime.exceptionHandler
+    // circom keeps running after reporting, so record the code to fail the calculation later
+    EXCEPTION.with(|e| e.set(Some(arg)));
+}
+wasm3::make_func_wrapper!(
+    print_error_message_wrap: print_error_message() -> ()
+);
+fn print_error_message() {
+    // Implementation for runtime.printErrorMessage
+    // the host function can't call back into the circuit, so the message is read from its
+    // buffer at the next check
+    ERROR_MESSAGE.with(|m| m.set(true));
+}
+wasm3::make_func_wrapper!(
+    write_buffer_message_wrap: write_buffer_message() -> ()
+);
+fn write_buffer_message() {
+    // Implementation for runtime.writeBufferMessage
+    // like `printErrorMessage`, the message is read from the buffer at the next check
+    BUFFER_MESSAGE.with(|m| m.set(true));
+}
+wasm3::make_func_wrapper!(
+    show_shared_rw_memory_wrap: show_shared_rw_memory() -> ()
+);
+fn show_shared_rw_memory() {
+    // Implementation for runtime.showSharedRWMemory
+    // circom logs signal values through the shared memory, which the host function can't
+    // read, so they are dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witness::circom::{from_array32, read_limbs_be};
+    use crate::witness::mock::MockCircom2;
+
+    fn multiplier_inputs() -> Vec<(String, Vec<BigInt>)> {
+        vec![
+            ("a".to_string(), vec![BigInt::from(3)]),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ]
+    }
+
+    #[test]
+    fn stored_sanity_check() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert!(!wtns.sanity_check());
+        wtns.set_sanity_check(true);
+        assert!(wtns.sanity_check());
+
+        let witness = wtns.calculate(multiplier_inputs()).unwrap();
+        let expected = wtns.calculate_witness(multiplier_inputs(), false).unwrap();
+        assert_eq!(witness, expected);
+        assert_eq!(&witness[..2], &[BigInt::from(1), BigInt::from(33)]);
+    }
+
+    #[test]
+    fn deterministic_witness() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let mut witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert!(wtns
+            .verify_deterministic(multiplier_inputs(), &witness, true)
+            .unwrap());
+        let first_index = |expected: &[BigInt]| {
+            wtns.first_nondeterministic_index(multiplier_inputs(), expected, true)
+                .unwrap()
+        };
+        assert_eq!(first_index(&witness), None);
+        assert_eq!(first_index(&witness[..3]), Some(3));
+        witness[2] += 1;
+        assert_eq!(first_index(&witness), Some(2));
+        assert!(!wtns
+            .verify_deterministic(multiplier_inputs(), &witness, true)
+            .unwrap());
+
+        let unknown = vec![("c".to_string(), vec![BigInt::from(3)])];
+        assert!(wtns.verify_deterministic(unknown, &witness, true).is_err());
+
+        // the calculator's own instance is left as is
+        let fresh = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert!(!fresh
+            .verify_deterministic(multiplier_inputs(), &witness[..2], true)
+            .unwrap());
+        assert_eq!(fresh.memory_size().unwrap(), None);
+    }
+
+    #[test]
+    fn direct_memory_access_matches_calls() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let direct = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        wtns.set_direct_memory_access(false);
+        let calls = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert_eq!(direct, calls);
+    }
+
+    #[test]
+    fn input_order_is_irrelevant() {
+        use super::super::WitnessInputs;
+        use std::collections::HashMap;
+
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let forward = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        let mut reversed = multiplier_inputs();
+        reversed.reverse();
+        let reversed = wtns.calculate_witness(reversed, true).unwrap();
+        let map: HashMap<_, _> = multiplier_inputs().into_iter().collect();
+        let map = wtns
+            .calculate_witness(WitnessInputs::from(map), true)
+            .unwrap();
+
+        assert_eq!(forward, reversed);
+        assert_eq!(forward, map);
+    }
+
+    #[test]
+    fn batch_keeps_going_on_error() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let expected = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+
+        let witnesses = wtns
+            .calculate_witnesses(vec![multiplier_inputs(), multiplier_inputs()], true)
+            .unwrap();
+        assert_eq!(witnesses, vec![expected.clone(), expected.clone()]);
+
+        let results = wtns
+            .calculate_witnesses_collect(vec![multiplier_inputs(), multiplier_inputs()], true)
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        for result in results {
+            assert_eq!(result.unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn batch_matches_single_calls() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let prime = wtns.prime().clone();
+        let batch = [(3, 11), (0, 5), (-1, 2)]
+            .iter()
+            .map(|&(a, b)| {
+                vec![
+                    ("a".to_string(), vec![BigInt::from(a)]),
+                    ("b".to_string(), vec![BigInt::from(b)]),
+                ]
+            })
+            .chain(std::iter::once(vec![
+                ("a".to_string(), vec![&prime - 1]),
+                ("b".to_string(), vec![&prime - 1]),
+            ]))
+            .collect::<Vec<_>>();
+
+        let witnesses = wtns.calculate_witnesses(batch.clone(), true).unwrap();
+        assert_eq!(witnesses.len(), batch.len());
+        for (inputs, witness) in batch.into_iter().zip(witnesses) {
+            assert_eq!(witness, wtns.calculate_witness(inputs, true).unwrap());
+        }
+    }
+
+    #[test]
+    fn columnar_batch() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let a = [3u32, 5, 7];
+        let b = [11u32, 13, 17];
+        let columns = vec![
+            (
+                "a".to_string(),
+                a.iter().map(|&v| BigInt::from(v)).collect(),
+            ),
+            (
+                "b".to_string(),
+                b.iter().map(|&v| BigInt::from(v)).collect(),
+            ),
+        ];
+        let witnesses = wtns
+            .calculate_witnesses_columnar(&columns, 3, true)
+            .unwrap();
+        assert_eq!(witnesses.len(), 3);
+        for ((a, b), witness) in a.iter().zip(&b).zip(&witnesses) {
+            let inputs = vec![
+                ("a".to_string(), vec![BigInt::from(*a)]),
+                ("b".to_string(), vec![BigInt::from(*b)]),
+            ];
+            assert_eq!(*witness, wtns.calculate_witness(inputs, true).unwrap());
+        }
+        assert!(wtns
+            .calculate_witnesses_columnar(&[], 0, true)
+            .unwrap()
+            .is_empty());
+
+        let err = wtns
+            .calculate_witnesses_columnar(&columns, 2, true)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("not a multiple of the batch size 2"));
+    }
+
+    #[test]
+    fn check_inputs() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        wtns.check_inputs(multiplier_inputs(), true).unwrap();
+
+        let unknown = vec![("c".to_string(), vec![BigInt::from(3)])];
+        let err = wtns.check_inputs(unknown.clone(), true).unwrap_err();
+        assert!(format!("{:?}", err).contains("Signal not found"));
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::CircomException(ExitCode(1)))
+        ));
+
+        wtns.set_exit_on_exception(false);
+        wtns.check_inputs(unknown, true).unwrap();
+        wtns.set_exit_on_exception(true);
+
+        let too_many = vec![("a".to_string(), vec![BigInt::from(3), BigInt::from(4)])];
+        let err = wtns.check_inputs(too_many, true).unwrap_err();
+        assert!(format!("{:?}", err).contains("a[1]"));
+    }
+
+    #[test]
+    fn duplicate_inputs() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let mut inputs = multiplier_inputs();
+        inputs.push(("a".to_string(), vec![BigInt::from(5)]));
+        let err = wtns.calculate_witness(inputs, true).unwrap_err();
+        assert!(err.to_string().contains("`a`"));
+    }
+
+    #[test]
+    fn embedded_bytes() {
+        let data: &'static [u8] = include_bytes!("../../test-vectors/multiplier2.wasm");
+        let wtns = WitnessCalculator::from_bytes(data).unwrap();
+        let witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert_eq!(witness[1], BigInt::from(33));
+    }
+
+    #[test]
+    fn reinit_keeps_signal_memory() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+
+        // a complete set of inputs on the reused instance matches a fresh calculator
+        let other = vec![
+            ("a".to_string(), vec![BigInt::from(2)]),
+            ("b".to_string(), vec![BigInt::from(5)]),
+        ];
+        wtns.reinit(false).unwrap();
+        let reused = wtns.calculate_witness(other.clone(), false).unwrap();
+        let fresh = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert_eq!(reused, fresh.calculate_witness(other, false).unwrap());
+
+        // `init` doesn't clear signals, only dropping the instance does
+        let partial = vec![("a".to_string(), vec![BigInt::from(7)])];
+        let witness = wtns.calculate_witness(partial.clone(), false).unwrap();
+        assert_eq!(witness[3], BigInt::from(5));
+        wtns.reset();
+        let witness = wtns.calculate_witness(partial, false).unwrap();
+        assert_eq!(witness[3], BigInt::from(0));
+    }
+
+    #[test]
+    fn wtns_output() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        let mut buf = Vec::new();
+        wtns.write_wtns(&witness, &mut buf).unwrap();
+
+        assert_eq!(&buf[..4], b"wtns");
+        // magic, version, sections, header section and witness section
+        assert_eq!(buf.len(), 12 + (12 + 4 + 32 + 4) + (12 + 4 * 32));
+        assert_eq!(&buf[28..60], &wtns.prime_le_bytes()[..]);
+        assert_eq!(buf[buf.len() - 32], 11);
+    }
+
+    #[test]
+    fn missing_inputs() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        wtns.calculate_witness_checked(multiplier_inputs(), true)
+            .unwrap();
+
+        let partial = vec![("a".to_string(), vec![BigInt::from(3)])];
+        let err = wtns.calculate_witness_checked(partial, true).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("1 of the circuit's 2 input values are unset"));
+
+        let empty = vec![
+            ("a".to_string(), vec![]),
+            ("b".to_string(), vec![BigInt::from(3)]),
+        ];
+        let err = wtns.calculate_witness_checked(empty, true).unwrap_err();
+        assert!(err.to_string().contains("`a` has 0 of its 1 values"));
+
+        let mut wrong = multiplier_inputs();
+        wrong[0].1.push(BigInt::from(4));
+        wrong.push(("c".to_string(), vec![BigInt::from(5)]));
+        let err = wtns.calculate_witness_checked(wrong, true).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Inputs don't match the circuit's signals: "));
+        assert!(err
+            .to_string()
+            .contains("`a` has 2 values but is declared with 1"));
+        assert!(err.to_string().contains("`c` is not an input signal"));
+        assert!(err
+            .to_string()
+            .contains("4 input values were provided but the circuit has 2"));
+    }
+
+    #[test]
+    fn input_size() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert_eq!(wtns.input_size().unwrap(), Some(2));
+
+        let wtns = WitnessCalculator::new("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        assert_eq!(wtns.input_size().unwrap(), None);
+    }
+
+    #[test]
+    fn detected_curve() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert_eq!(wtns.detected_curve(), Some(KnownCurve::Bn254));
+
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let curve = |prime: BigInt| {
+            WitnessCalculator::from_bytes_with_prime(&data, prime)
+                .unwrap()
+                .detected_curve()
+        };
+        assert_eq!(curve(prime25519()), Some(KnownCurve::Ed25519));
+        assert_eq!(
+            curve(BigInt::from(GOLDILOCKS)),
+            Some(KnownCurve::Goldilocks)
+        );
+        assert_eq!(curve(BigInt::from(1_000_000_007)), None);
+    }
+
+    #[test]
+    fn summary() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert_eq!(
+            wtns.summary().unwrap(),
+            "circom 2 circuit, 254-bit prime field, 2 inputs, 4 witness signals"
+        );
+
+        let wtns = WitnessCalculator::new("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        assert_eq!(
+            wtns.summary().unwrap(),
+            "circom 2 circuit, 254-bit prime field, 4 witness signals"
+        );
+    }
+
+    #[test]
+    fn circuit_cost() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let cost = wtns.cost().unwrap();
+        assert_eq!(
+            cost,
+            CircuitCost {
+                field_bits: 254,
+                field_bytes: 32,
+                witness_size: 4,
+                input_size: Some(2),
+            }
+        );
+        assert_eq!(cost.witness_bytes(), 128);
+    }
+
+    #[test]
+    fn reproducible_runs() {
+        let mut first = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        first.set_reproducible(true);
+        let second = first.clone();
+        let witness = first.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert_eq!(
+            witness,
+            second.calculate_witness(multiplier_inputs(), true).unwrap()
+        );
+        assert_eq!(
+            witness,
+            first.calculate_witness(multiplier_inputs(), true).unwrap()
+        );
+
+        let linked = vec![
+            ("runtime".to_string(), "showSharedRWMemory".to_string()),
+            ("runtime".to_string(), "exceptionHandler".to_string()),
+        ];
+        assert!(check_link_order(&linked).is_err());
+        assert!(check_link_order(&[("env".to_string(), "log".to_string())]).is_err());
+    }
+
+    #[test]
+    fn runtime_access() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let call = |name: &'static str| {
+            wtns.with_runtime(move |runtime| {
+                let func = runtime
+                    .find_function::<(), i32>(name)
+                    .map_err(|e| eyre!("{}: {}", name, e))?;
+                func.call().map_err(|e| eyre!("{}: {}", name, e))
+            })
+        };
+        assert_eq!(call("getVersion").unwrap(), 2);
+        assert!(call("getNothing").is_err());
+
+        // calculations keep running on the same instance
+        let witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert_eq!(witness[1], BigInt::from(33));
+        assert_eq!(call("getWitnessSize").unwrap(), 4);
+        #[cfg(feature = "call-stats")]
+        assert_eq!(wtns.call_stats().unwrap().calls("getWitness"), 4);
+    }
+
+    #[test]
+    fn memory_size() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert_eq!(wtns.memory_size().unwrap(), None);
+
+        wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        let size = wtns.memory_size().unwrap().unwrap();
+        // a whole number of 64KiB wasm pages
+        assert!(size > 0);
+        assert_eq!(size % 65536, 0);
+
+        wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert_eq!(wtns.memory_size().unwrap(), Some(size));
+    }
+
+    #[test]
+    fn lazy_witness_element() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert!(wtns.witness_element(0).is_err());
+
+        let witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        for (i, w) in witness.iter().enumerate() {
+            assert_eq!(&wtns.witness_element(i as u32).unwrap(), w);
+        }
+        assert!(wtns.witness_element(witness.len() as u32).is_err());
+    }
+
+    #[cfg(feature = "arkworks")]
+    #[test]
+    fn field_inputs() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let expected = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+
+        let inputs = vec![
+            ("a".to_string(), vec![ark_bn254::Fr::from(3u64)]),
+            ("b".to_string(), vec![ark_bn254::Fr::from(11u64)]),
+        ];
+        let witness = wtns.calculate_witness_from_field(inputs, true).unwrap();
+        assert_eq!(witness, expected);
+
+        let inputs = vec![("a".to_string(), vec![ark_bn254::Fq::from(3u64)])];
+        assert!(wtns.calculate_witness_from_field(inputs, true).is_err());
+    }
+
+    #[test]
+    fn missing_export_is_named() {
+        let wtns = WitnessCalculator::new("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        let err = wtns
+            .with_wasm(|instance| instance.get_u32("getInputSize"))
+            .unwrap_err();
+        assert!(err.to_string().contains("getInputSize"));
+    }
+
+    #[test]
+    fn shared_calculator() {
+        let wtns =
+            std::rc::Rc::new(WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap());
+        let handler = {
+            let wtns = wtns.clone();
+            move || wtns.calculate_witness(multiplier_inputs(), true)
+        };
+        assert_eq!(handler().unwrap()[1], BigInt::from(33));
+
+        let err = wtns
+            .with_wasm(|_| wtns.calculate_witness(multiplier_inputs(), true))
+            .unwrap_err();
+        assert!(err.to_string().contains("while running a calculation"));
+    }
+
+    // A circuit without inputs whose witness is `[1, 42]`, as circom would compile
+    // `template Constant() { signal output out <== 42; }`, over a 64-bit field padded to
+    // eight words
+    fn constant() -> MockCircom2 {
+        MockCircom2::new(
+            BigInt::from(u64::MAX),
+            vec![BigInt::from(1), BigInt::from(42)],
+        )
+        .with_n32(8)
+        .with_signals(&[])
+    }
+
+    #[test]
+    fn other_engine() {
+        let circuit = constant();
+        assert_eq!(circuit.prime().unwrap(), BigInt::from(u64::MAX));
+        let witness = calculate_witness_on(&circuit, std::iter::empty(), true).unwrap();
+        assert_eq!(witness, vec![BigInt::from(1), BigInt::from(42)]);
+    }
+
+    #[test]
+    fn limbs_most_significant_first() {
+        let circuit = constant();
+        circuit.get_raw_prime().unwrap();
+        assert_eq!(
+            read_limbs_be(&circuit, 8).unwrap(),
+            [0, 0, 0, 0, 0, 0, u32::MAX, u32::MAX]
+        );
+        // only the low words are read for a smaller n32
+        assert_eq!(read_limbs_be(&circuit, 2).unwrap(), [u32::MAX, u32::MAX]);
+    }
+
+    #[test]
+    fn bounded_field_elements() {
+        assert_eq!(
+            from_array32(vec![1, 0]).unwrap(),
+            BigInt::from(0x100000000u64)
+        );
+        let err = from_array32(vec![0; 65]).unwrap_err();
+        assert!(err.to_string().contains("at most 64"));
+        check_n32(0).unwrap_err();
+        check_n32(u32::MAX).unwrap_err();
+    }
+
+    #[cfg(feature = "arkworks")]
+    #[derive(ark_ff::MontConfig)]
+    #[modulus = "18446744069414584321"]
+    #[generator = "7"]
+    struct GoldilocksConfig;
+    #[cfg(feature = "arkworks")]
+    type Goldilocks = ark_ff::Fp64<ark_ff::MontBackend<GoldilocksConfig, 1>>;
+
+    const GOLDILOCKS: u64 = 18446744069414584321;
+
+    #[test]
+    fn goldilocks() {
+        // stands in for the multiplier2 circuit compiled with `--prime goldilocks` (n32 = 2),
+        // with the witness `[1, a * b, a, b]` of `a = -2` and `b = -3`, to check the input
+        // limbs; tests/conformance.rs runs the compiled circuit
+        let (a, b) = (GOLDILOCKS - 2, GOLDILOCKS - 3);
+        let expected = [1, 6, a, b].map(BigInt::from).to_vec();
+        let circuit = MockCircom2::new(BigInt::from(GOLDILOCKS), expected.clone())
+            .with_signals(&[("a", 1), ("b", 1)]);
+        assert_eq!(circuit.prime().unwrap(), BigInt::from(GOLDILOCKS));
+        assert_eq!(circuit.get_field_num_len32().unwrap(), 2);
+
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(a)]),
+            ("b".to_string(), vec![BigInt::from(-3)]),
+        ];
+        let witness = calculate_witness_on(&circuit, inputs, true).unwrap();
+        assert_eq!(witness, expected);
+        assert_eq!(circuit.input("a", 0), Some(BigInt::from(a)));
+        assert_eq!(circuit.input("b", 0), Some(BigInt::from(b)));
+
+        #[cfg(feature = "arkworks")]
+        {
+            let elements = to_field_elements::<Goldilocks>(&witness).unwrap();
+            assert_eq!(elements[1], Goldilocks::from(6u64));
+            assert_eq!(elements[2], -Goldilocks::from(2u64));
+        }
+
+        // values above the prime are rejected even though they fit into the two words
+        let inputs = vec![("a".to_string(), vec![BigInt::from(GOLDILOCKS)])];
+        assert!(calculate_witness_on(&circuit, inputs, true).is_err());
+    }
+
+    #[cfg(feature = "arkworks")]
+    #[derive(ark_ff::MontConfig)]
+    #[modulus = "57896044618658097711785492504343953926634992332820282019728792003956564819949"]
+    #[generator = "2"]
+    struct Field25519Config;
+    #[cfg(feature = "arkworks")]
+    type Field25519 = ark_ff::Fp256<ark_ff::MontBackend<Field25519Config, 4>>;
+
+    // 2^255 - 19, the base field of curve25519
+    fn prime25519() -> BigInt {
+        (BigInt::from(1) << 255) - 19
+    }
+
+    #[test]
+    fn curve25519_field() {
+        // stands in for a multiplier over the curve25519 base field (n32 = 8), with the
+        // witness `[1, a * b, a, b]` of `a = -2` and `b = -3`; circom has no `--prime` for
+        // this field, so there is no compiled circuit to check against
+        let prime = prime25519();
+        let expected = vec![BigInt::from(1), BigInt::from(6), &prime - 2, &prime - 3];
+        let circuit =
+            MockCircom2::new(prime.clone(), expected.clone()).with_signals(&[("a", 1), ("b", 1)]);
+        assert_eq!(circuit.prime().unwrap(), prime);
+        assert_eq!(circuit.get_field_num_len32().unwrap(), 8);
+        assert_eq!(prime.bits(), 255);
+
+        let inputs = vec![
+            ("a".to_string(), vec![&prime - 2]),
+            ("b".to_string(), vec![BigInt::from(-3)]),
+        ];
+        let witness = calculate_witness_on(&circuit, inputs, true).unwrap();
+        assert_eq!(witness, expected);
+        assert_eq!(circuit.input("b", 0), Some(&prime - 3));
+
+        #[cfg(feature = "arkworks")]
+        {
+            let elements = to_field_elements::<Field25519>(&witness).unwrap();
+            assert_eq!(elements[1], Field25519::from(6u64));
+            assert_eq!(elements[2], -Field25519::from(2u64));
+            assert_eq!(elements[3], -Field25519::from(3u64));
+        }
+
+        let inputs = vec![("a".to_string(), vec![prime.clone()])];
+        assert!(calculate_witness_on(&circuit, inputs, true).is_err());
+    }
+
+    #[test]
+    fn n64_at_word_boundaries() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let n64 = |prime: BigInt| {
+            WitnessCalculator::from_bytes_with_prime(&data, prime)
+                .unwrap()
+                .n64
+        };
+        assert_eq!(n64(BigInt::from(GOLDILOCKS)), 1);
+        assert_eq!(n64(BigInt::from(u64::MAX)), 1);
+        assert_eq!(n64((BigInt::from(1) << 64) + 13), 2);
+        assert_eq!(n64(prime25519()), 4);
+        assert_eq!(n64((BigInt::from(1) << 256) - 189), 4);
+        assert_eq!(n64((BigInt::from(1) << 256) + 297), 5);
+    }
+
+    #[test]
+    fn zero_inputs() {
+        let prime = BigInt::from(u64::MAX);
+        let options = RunOptions {
+            prime: &prime,
+            limits: Limits::default(),
+            sanity_check: true,
+            exit_on_exception: true,
+            messages: None,
+        };
+        let circuit = constant();
+        validate_inputs(&circuit, &[]).unwrap();
+        let witness = run(&circuit, std::iter::empty(), options).unwrap();
+        assert_eq!(witness, vec![BigInt::from(1), BigInt::from(42)]);
+
+        let inputs = vec![("a".to_string(), vec![BigInt::from(3)])];
+        assert!(run(&circuit, inputs, options).is_err());
+    }
+
+    #[cfg(feature = "arkworks")]
+    #[test]
+    fn out_of_range_element() {
+        use ark_ff::PrimeField;
+        let modulus: BigUint = ark_bn254::Fr::MODULUS.into();
+        let p = BigInt::from(modulus.clone());
+
+        let minus_one = to_field_element::<ark_bn254::Fr>(&BigInt::from(-1), &modulus).unwrap();
+        assert_eq!(minus_one, -ark_bn254::Fr::from(1u64));
+        assert!(to_field_element::<ark_bn254::Fr>(&p, &modulus).is_err());
+        assert!(to_field_element::<ark_bn254::Fr>(&(-&p - 1), &modulus).is_err());
+    }
+
+    #[test]
+    fn out_of_range_input() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let big = BigInt::from(1) << 259;
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(3)]),
+            ("b".to_string(), vec![big]),
+        ];
+        let err = wtns.calculate_witness(inputs, true).unwrap_err();
+        assert!(err.to_string().contains("b[0]"));
+        assert!(format!("{:?}", err).contains("input has 260 bits but field is 254 bits"));
+
+        // negative inputs are taken modulo the prime
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(-3)]),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ];
+        let witness = wtns.calculate_witness(inputs, true).unwrap();
+        assert_eq!(witness[1], wtns.prime() - 33);
+    }
+
+    #[cfg(feature = "arkworks")]
+    #[test]
+    fn witness_with_elements() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let (witness, elements) = wtns
+            .calculate_witness_with_elements::<ark_bn254::Bn254, _>(multiplier_inputs(), true)
+            .unwrap();
+        assert_eq!(
+            witness,
+            wtns.calculate_witness(multiplier_inputs(), true).unwrap()
+        );
+        assert_eq!(
+            elements,
+            wtns.calculate_witness_element::<ark_bn254::Bn254, _>(multiplier_inputs(), true)
+                .unwrap()
+        );
+    }
+
+    #[cfg(feature = "r1cs")]
+    #[test]
+    fn r1cs_satisfied() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let mut witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        let r1cs = "./test-vectors/multiplier2.r1cs";
+        assert_eq!(
+            wtns.check_witness_r1cs::<ark_bn254::Bn254>(r1cs, &witness)
+                .unwrap(),
+            None
+        );
+
+        witness[1] += 1;
+        assert_eq!(
+            wtns.check_witness_r1cs::<ark_bn254::Bn254>(r1cs, &witness)
+                .unwrap(),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn lazy_inputs() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert!(wtns.calculate_witness_lazy(|_| None, true).is_err());
+
+        wtns.load_symbols("./test-vectors/multiplier2.sym").unwrap();
+        let mut requested = Vec::new();
+        let witness = wtns
+            .calculate_witness_lazy(
+                |name| {
+                    requested.push(name.to_string());
+                    let value = if name == "a" { 3 } else { 11 };
+                    Some(vec![BigInt::from(value)])
+                },
+                true,
+            )
+            .unwrap();
+        assert_eq!(requested, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(witness[1], BigInt::from(33));
+
+        // circom 2.0 circuits don't report their input signals
+        let mut wtns = WitnessCalculator::new("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        wtns.load_symbols("./test-vectors/multiplier2.sym").unwrap();
+        let err = wtns.calculate_witness_lazy(|_| None, true).unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::MissingExport(_))
+        ));
+    }
+
+    #[cfg(feature = "arkworks")]
+    #[test]
+    fn canonical_witness() {
+        use ark_serialize::CanonicalDeserialize;
+
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let mut bytes = Vec::new();
+        wtns.calculate_witness_canonical::<ark_bn254::Bn254, _, _>(
+            multiplier_inputs(),
+            true,
+            &mut bytes,
+        )
+        .unwrap();
+        let witness = Vec::<ark_bn254::Fr>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(witness, [1, 33, 3, 11].map(ark_bn254::Fr::from).to_vec());
+    }
+
+    #[test]
+    fn input_schema() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert!(wtns.input_schema().is_empty());
+
+        wtns.load_symbols("./test-vectors/multiplier2.sym").unwrap();
+        assert_eq!(
+            wtns.input_schema(),
+            &[("a".to_string(), 1), ("b".to_string(), 1)]
+        );
+
+        // the checked path validates against the schema, with the same errors
+        let witness = wtns
+            .calculate_witness_checked(multiplier_inputs(), true)
+            .unwrap();
+        assert_eq!(witness[1], BigInt::from(33));
+        let mut wrong = multiplier_inputs();
+        wrong[0].1.push(BigInt::from(4));
+        wrong.push(("c".to_string(), vec![BigInt::from(5)]));
+        let err = wtns.calculate_witness_checked(wrong, true).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("`a` has 2 values but is declared with 1"));
+        assert!(err.to_string().contains("`c` is not an input signal"));
+        let err = wtns
+            .calculate_witness_checked(multiplier_inputs()[..1].to_vec(), true)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("1 of the circuit's 2 input values are unset"));
+
+        // replacing the symbols replaces the schema
+        wtns.set_symbols(Vec::new()).unwrap();
+        assert!(wtns.input_schema().is_empty());
+    }
+
+    #[test]
+    fn linked_imports() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let status = wtns.import_status().unwrap();
+        assert_eq!(status.len(), 4);
+        assert!(status.iter().all(|(_, linked)| *linked));
+        assert_eq!(status[0].0, "runtime.exceptionHandler");
+    }
+
+    #[test]
+    fn not_a_wasm_file() {
+        let err = WitnessCalculator::from_file("./test-vectors/multiplier2.r1cs").unwrap_err();
+        assert!(matches!(CircomError::of(&err), Some(CircomError::Load(_))));
+        assert!(format!("{:#}", err)
+            .contains("The file is an .r1cs constraint system, not a wasm witness generator"));
+
+        let err = WitnessCalculator::from_file("./test-vectors/test.zkey").unwrap_err();
+        assert!(format!("{:#}", err).contains("a .zkey proving key"));
+
+        let err = WitnessCalculator::from_bytes(b"{}").unwrap_err();
+        assert_eq!(err.to_string(), "The file is not a wasm module");
+
+        let err = WitnessCalculator::from_bytes(&[]).unwrap_err();
+        assert_eq!(err.to_string(), "The file is empty, not a wasm module");
+        let err = WitnessCalculator::from_bytes_with_prime(&[], BigInt::from(7)).unwrap_err();
+        assert!(matches!(CircomError::of(&err), Some(CircomError::Load(_))));
+        let err = WitnessCalculator::from_bytes(b"\0asm\x01").unwrap_err();
+        assert_eq!(err.to_string(), "The wasm module header is truncated");
+    }
+
+    // Returns a wasm binary with an export section of the functions `names`, which is all
+    // `check_exports` reads
+    fn module_exporting(names: &[&str]) -> Vec<u8> {
+        let mut section = vec![names.len() as u8];
+        for (i, name) in names.iter().enumerate() {
+            section.push(name.len() as u8);
+            section.extend_from_slice(name.as_bytes());
+            section.extend_from_slice(&[0, i as u8]);
+        }
+        let mut data = b"\0asm\x01\0\0\0".to_vec();
+        // the section size as a two byte LEB128
+        let size = section.len() as u16;
+        data.extend_from_slice(&[7, (size & 0x7f) as u8 | 0x80, (size >> 7) as u8]);
+        data.extend(section);
+        data
+    }
+
+    #[test]
+    fn export_layout() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        check_exports(&data).unwrap();
+
+        let err = WitnessCalculator::new("./test-vectors/mycircuit.wasm").unwrap_err();
+        assert!(format!("{:#}", err).contains("compiled with circom 1"));
+
+        let err = check_exports(&module_exporting(&REQUIRED_EXPORTS[1..])).unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::MissingExport(name)) if name == "init"
+        ));
+
+        let mut names = REQUIRED_EXPORTS.to_vec();
+        names.push("main2_init");
+        let err = check_exports(&module_exporting(&names)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("several witness generators (init, main2_init)"));
+
+        // only a separator makes a prefixed `init` another entry point
+        names.pop();
+        names.push("reinit");
+        check_exports(&module_exporting(&names)).unwrap();
+    }
+
+    #[test]
+    fn signal_values() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        wtns.load_symbols("./test-vectors/multiplier2.sym").unwrap();
+        wtns.witness_for_signal("main.c").unwrap_err();
+
+        wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert_eq!(wtns.witness_for_signal("main.c").unwrap(), BigInt::from(33));
+        assert_eq!(wtns.witness_for_signal("main.b").unwrap(), BigInt::from(11));
+        wtns.witness_for_signal("main.d").unwrap_err();
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn streamed_json_inputs() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let json = r#"{"a": "3", "b": [11]}"#;
+        assert_eq!(
+            wtns.calculate_witness_from_json(json.as_bytes(), true)
+                .unwrap(),
+            wtns.calculate_witness(multiplier_inputs(), true).unwrap()
+        );
+
+        let json = r#"{"a": 3, "a": 4}"#;
+        let err = wtns
+            .calculate_witness_from_json(json.as_bytes(), true)
+            .unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::Input(InputError::Duplicate(_)))
+        ));
+    }
+
+    #[test]
+    fn single_prime() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let prime = wtns.prime().clone();
+        assert_eq!(wtns.n64, 4);
+
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(3)]),
+            ("b".to_string(), vec![BigInt::from(-1)]),
+        ];
+        let witness = wtns.calculate_witness(inputs, true).unwrap();
+        assert_eq!(witness[1], &prime - 3);
+        assert_eq!(
+            wtns.likely_negative_elements(&witness),
+            vec![(1, BigInt::from(-3)), (3, BigInt::from(-1))]
+        );
+        let reduced = wtns
+            .calculate_witness_reduced(multiplier_inputs(), true)
+            .unwrap();
+        assert_eq!(reduced[1], BigUint::from(33u32));
+
+        let mut buf = Vec::new();
+        wtns.write_wtns(&witness, &mut buf).unwrap();
+        assert_eq!(&buf[28..60], &wtns.prime_le_bytes()[..]);
+        assert_eq!(BigInt::from_bytes_le(Sign::Plus, &buf[28..60]), prime);
+        // the second element, after the header and the witness section's own header
+        assert_eq!(
+            BigInt::from_bytes_le(Sign::Plus, &buf[108..140]),
+            &prime - 3
+        );
+    }
+
+    #[test]
+    fn instance_is_loaded_lazily() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert_eq!(wtns.memory_size().unwrap(), None);
+        assert_eq!(wtns.prime().bits(), 254);
+        assert_eq!(wtns.memory_size().unwrap(), None);
+
+        wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert!(wtns.memory_size().unwrap().is_some());
+    }
+
+    #[test]
+    fn instance_stays_on_its_thread() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let expected = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+
+        // the calculator moves, its instance doesn't
+        let wtns = std::thread::spawn(move || {
+            assert_eq!(wtns.memory_size().unwrap(), None);
+            assert_eq!(
+                wtns.calculate_witness(multiplier_inputs(), true).unwrap(),
+                expected
+            );
+            wtns
+        })
+        .join()
+        .unwrap();
+        assert!(wtns.memory_size().unwrap().is_some());
+        drop(wtns);
+        INSTANCES.with(|instances| assert!(instances.borrow().is_empty()));
+    }
+
+    #[test]
+    fn named_witness() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let named = wtns
+            .calculate_witness_named(multiplier_inputs(), true, "./test-vectors/multiplier2.sym")
+            .unwrap();
+        assert_eq!(
+            named,
+            vec![
+                ("main.c".to_string(), BigInt::from(33)),
+                ("main.a".to_string(), BigInt::from(3)),
+                ("main.b".to_string(), BigInt::from(11)),
+            ]
+        );
+    }
+
+    #[test]
+    fn witness_requires_init() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let err = wtns
+            .with_wasm(|instance| instance.get_witness(0))
+            .unwrap_err();
+        assert!(err.to_string().contains("wasn't initialized"));
+
+        wtns.reinit(true).unwrap();
+        wtns.with_wasm(|instance| instance.get_witness(0)).unwrap();
+        wtns.reset();
+        assert!(wtns.with_wasm(|instance| instance.get_witness(0)).is_err());
+
+        // a calculation on the reused instance must run `init` again
+        wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        let (msb, lsb) = fnv("a");
+        let set_a = || wtns.with_wasm(|instance| instance.set_input_signal(msb, lsb, 0));
+        let err = set_a().unwrap_err();
+        assert!(err.to_string().contains("since its last calculation"));
+        assert_eq!(wtns.witness_element(1).unwrap(), BigInt::from(33));
+        wtns.reinit(true).unwrap();
+        set_a().unwrap();
+    }
+
+    #[test]
+    fn outputs_only() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let err = wtns
+            .calculate_outputs(multiplier_inputs(), true)
+            .unwrap_err();
+        assert!(err.to_string().contains("without a symbol table"));
+
+        wtns.load_symbols("./test-vectors/multiplier2.sym").unwrap();
+        assert_eq!(wtns.num_outputs().unwrap(), 1);
+        let outputs = wtns.calculate_outputs(multiplier_inputs(), true).unwrap();
+        assert_eq!(outputs, vec![BigInt::from(33)]);
+    }
+
+    #[cfg(feature = "call-stats")]
+    #[test]
+    fn counts_calls() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert_eq!(wtns.call_stats().unwrap().total(), 0);
+
+        wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        let stats = wtns.call_stats().unwrap();
+        assert_eq!(stats.calls("init"), 1);
+        assert_eq!(stats.calls("setInputSignal"), 2);
+        assert_eq!(stats.calls("getWitness"), 4);
+        assert_eq!(stats.calls("readSharedRWMemory"), 0);
+
+        wtns.set_direct_memory_access(false);
+        wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        let stats = wtns.call_stats().unwrap();
+        assert_eq!(stats.calls("readSharedRWMemory"), 4 * 8);
+        assert_eq!(stats.calls("writeSharedRWMemory"), 2 * 8);
+    }
+
+    #[test]
+    fn scalar_inputs() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let witness = wtns
+            .calculate_witness_scalars(vec![("a", BigInt::from(3)), ("b", BigInt::from(11))], true)
+            .unwrap();
+        assert_eq!(
+            witness,
+            wtns.calculate_witness(multiplier_inputs(), true).unwrap()
+        );
+    }
+
+    #[test]
+    fn update_input() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        let err = wtns
+            .update_input_and_recompute("a", vec![BigInt::from(5)])
+            .unwrap_err();
+        assert!(err.to_string().contains("set_input_caching"));
+
+        wtns.set_input_caching(true);
+        let err = wtns
+            .update_input_and_recompute("a", vec![BigInt::from(5)])
+            .unwrap_err();
+        assert!(err.to_string().contains("No witness"));
+
+        wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        let witness = wtns
+            .update_input_and_recompute("a", vec![BigInt::from(5)])
+            .unwrap();
+        assert_eq!(witness, [1, 55, 5, 11].map(BigInt::from));
+
+        // the update is kept for the next one
+        let witness = wtns
+            .update_input_and_recompute("b", vec![BigInt::from(2)])
+            .unwrap();
+        assert_eq!(witness, [1, 10, 5, 2].map(BigInt::from));
+
+        // a failed update leaves the cached inputs unchanged
+        wtns.update_input_and_recompute("c", vec![BigInt::from(1)])
+            .unwrap_err();
+        let witness = wtns
+            .update_input_and_recompute("a", vec![BigInt::from(3)])
+            .unwrap();
+        assert_eq!(witness, [1, 6, 3, 2].map(BigInt::from));
+    }
+
+    #[test]
+    fn error_categories() {
+        let err = WitnessCalculator::from_bytes(b"not a circuit").unwrap_err();
+        assert!(matches!(CircomError::of(&err), Some(CircomError::Load(_))));
+
+        let err = WitnessCalculator::new("./test-vectors/missing.wasm").unwrap_err();
+        assert!(matches!(CircomError::of(&err), Some(CircomError::Io(_))));
+
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let mut inputs = multiplier_inputs();
+        inputs.push(("a".to_string(), vec![BigInt::from(5)]));
+        let err = wtns.calculate_witness(inputs, true).unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::Input(InputError::Duplicate(name))) if name == "a"
+        ));
+
+        let unknown = vec![("c".to_string(), vec![BigInt::from(3)])];
+        let err = wtns.calculate_witness(unknown, true).unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::CircomException(ExitCode(1)))
+        ));
+
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let loaded = Instance::load(
+            &data,
+            &runtime_imports(),
+            None,
+            METADATA_RUNTIME_SIZE,
+            false,
+        )
+        .unwrap();
+        let instance = loaded.wasm(false);
+        let err = instance.get_u32("getNothing").unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::MissingExport(name)) if name == "getNothing"
+        ));
+        let err = instance.read_shared_rw_memory(1 << 24).unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::Trap { function, .. }) if function == "readSharedRWMemory"
+        ));
+
+        // `exceptionHandler` takes the exception code
+        let mut wtns = WitnessCalculator::from_bytes(&data).unwrap();
+        wtns.register_import(RUNTIME_MODULE, "exceptionHandler", |module, m, n| {
+            module.link_function::<(), ()>(m, n, print_error_message_wrap)
+        });
+        let err = wtns
+            .calculate_witness(multiplier_inputs(), true)
+            .unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::Link { import, .. }) if import == "runtime.exceptionHandler"
+        ));
+    }
+
+    #[test]
+    fn shared_memory_bounds() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        for direct_memory in [true, false] {
+            wtns.set_direct_memory_access(direct_memory);
+            wtns.with_wasm(|instance| {
+                instance.write_shared_rw_memory(7, 1)?;
+                assert_eq!(instance.read_shared_rw_memory(7)?, 1);
+                instance.read_shared_rw_memory_bulk(&mut [0; 8])?;
+
+                instance.read_shared_rw_memory(8).unwrap_err();
+                instance.write_shared_rw_memory(8, 1).unwrap_err();
+                instance.write_shared_rw_memory_bulk(&[0; 9]).unwrap_err();
+                instance
+                    .read_shared_rw_memory_bulk(&mut [0; 9])
+                    .unwrap_err();
+                Ok(())
+            })
+            .unwrap();
+        }
+    }
+
+    // Hands out the characters of a message through `getMessageChar`
+    struct MessageBuffer(RefCell<std::vec::IntoIter<u8>>);
+
+    impl CircomBase for MessageBuffer {
+        fn init(&self, _sanity_check: bool) -> Result<()> {
+            Ok(())
+        }
+
+        fn get_u32(&self, name: &str) -> Result<u32> {
+            assert_eq!(name, "getMessageChar");
+            Ok(self.0.borrow_mut().next().unwrap_or(0) as u32)
+        }
+
+        fn get_version(&self) -> Result<CircomVersion> {
+            Ok(CircomVersion::V2(2))
+        }
+    }
+
+    #[test]
+    fn reads_message_buffer() {
+        let instance = MessageBuffer(RefCell::new(b"x = 3 \n\0rest".to_vec().into_iter()));
+        assert_eq!(instance.read_message_buffer(), "x = 3");
+        assert_eq!(instance.read_message_buffer(), "rest");
+        assert_eq!(instance.read_message_buffer(), "");
+
+        let unterminated = vec![b'a'; 5000];
+        let instance = MessageBuffer(RefCell::new(unterminated.into_iter()));
+        assert_eq!(instance.read_message_buffer().len(), 4096);
+
+        // logged messages are handed to the handler without failing the calculation
+        let prime = BigInt::from(7);
+        let received = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = {
+            let received = received.clone();
+            MessageSink(Arc::new(move |m: &str| {
+                received.lock().unwrap().push(m.to_string())
+            }))
+        };
+        let mut options = RunOptions {
+            prime: &prime,
+            limits: Limits::default(),
+            sanity_check: true,
+            exit_on_exception: true,
+            messages: Some(&sink),
+        };
+        let instance = MessageBuffer(RefCell::new(b"log\0ignored".to_vec().into_iter()));
+        write_buffer_message();
+        check_exception(&instance, options).unwrap();
+
+        // as are the messages of ignored exceptions
+        options.exit_on_exception = false;
+        print_error_message();
+        exception_handler(4);
+        check_exception(&instance, options).unwrap();
+        assert_eq!(*received.lock().unwrap(), vec!["log", "ignored"]);
+
+        // and nothing is read without a handler
+        options.messages = None;
+        let instance = MessageBuffer(RefCell::new(b"log".to_vec().into_iter()));
+        write_buffer_message();
+        check_exception(&instance, options).unwrap();
+        assert_eq!(instance.0.borrow().len(), 3);
+    }
+
+    #[test]
+    fn error_message_attached() {
+        let prime = BigInt::from(7);
+        let options = RunOptions {
+            prime: &prime,
+            limits: Limits::default(),
+            sanity_check: true,
+            exit_on_exception: true,
+            messages: None,
+        };
+        let message = b"Error in template Num2Bits_0 line: 38\n".to_vec();
+        let instance = MessageBuffer(RefCell::new(message.into_iter()));
+
+        print_error_message();
+        exception_handler(4);
+        let err = check_exception(&instance, options).unwrap_err();
+        assert_eq!(err.to_string(), "Error in template Num2Bits_0 line: 38");
+        assert_eq!(
+            super::super::ErrorLocation::of(&err),
+            Some(super::super::ErrorLocation {
+                template: "Num2Bits_0".to_string(),
+                line: 38,
+            })
+        );
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::CircomException(ExitCode(4)))
+        ));
+
+        // nothing is read without a printed message
+        exception_handler(4);
+        let err = check_exception(&instance, options).unwrap_err();
+        assert_eq!(err.to_string(), "Circom exception 4: Assert Failed");
+        assert_eq!(super::super::ErrorLocation::of(&err), None);
+        check_exception(&instance, options).unwrap();
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn same_witness_across_calculators() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let wtns = WitnessCalculator::from_bytes(&data).unwrap();
+        let other = WitnessCalculator::from_bytes(&data).unwrap();
+        let witness = wtns.assert_same_witness(&other, &multiplier_inputs(), true);
+        assert_eq!(witness[1], BigInt::from(33));
+        wtns.assert_same_witness(&wtns.clone(), &multiplier_inputs(), false);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    #[should_panic(expected = "The first calculation failed")]
+    fn same_witness_of_failing_inputs() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let unknown = [("c".to_string(), vec![BigInt::from(3)])];
+        wtns.assert_same_witness(&wtns.clone(), &unknown, true);
+    }
+
+    #[test]
+    fn partial_witness() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let partial = wtns
+            .calculate_witness_partial(multiplier_inputs(), true)
+            .unwrap();
+        assert!(partial.exception.is_none());
+        assert_eq!(
+            partial.witness,
+            wtns.calculate_witness(multiplier_inputs(), true).unwrap()
+        );
+
+        let too_many = vec![
+            ("a".to_string(), vec![BigInt::from(3), BigInt::from(4)]),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ];
+        let partial = wtns.calculate_witness_partial(too_many, true).unwrap();
+        assert!(matches!(
+            CircomError::of(&partial.exception.unwrap()),
+            Some(CircomError::CircomException(ExitCode(6)))
+        ));
+        assert_eq!(partial.witness.len(), 4);
+        assert_eq!(partial.witness[0], BigInt::from(1));
+
+        // errors other than exceptions aren't captured
+        let mut duplicate = multiplier_inputs();
+        duplicate.push(("a".to_string(), vec![BigInt::from(5)]));
+        assert!(wtns.calculate_witness_partial(duplicate, true).is_err());
+    }
+
+    #[test]
+    fn exceptions_without_sanity_check() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let too_many = vec![
+            ("a".to_string(), vec![BigInt::from(3), BigInt::from(4)]),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ];
+        let err = wtns.calculate_witness(too_many, false).unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::CircomException(ExitCode(6)))
+        ));
+    }
+
+    #[test]
+    fn raw_bytes() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let bytes = wtns
+            .calculate_witness_raw_bytes(multiplier_inputs(), true)
+            .unwrap();
+        let witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+
+        let mut wtns_file = Vec::new();
+        wtns.write_wtns(&witness, &mut wtns_file).unwrap();
+        assert!(wtns_file.ends_with(&bytes));
+        assert_eq!(bytes.len(), witness.len() * 32);
+    }
+
+    #[test]
+    fn verified_hash() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let wtns = WitnessCalculator::from_bytes(&data).unwrap();
+        let hash = wtns.circuit_hash();
+        assert_eq!(
+            hex::encode(hash),
+            "6f71a473fb4d89ea5f4f606bace660edc1e3ed8fa354771a563f803971dd7759"
+        );
+
+        WitnessCalculator::from_bytes_verified(&data, &hash).unwrap();
+        let err = WitnessCalculator::from_bytes_verified(&data, &[0; 32]).unwrap_err();
+        assert!(err.to_string().contains("does not match"));
+    }
+
+    #[test]
+    fn limits() {
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        wtns.set_limits(Limits {
+            max_witness_size: 3,
+            ..Limits::default()
+        });
+        let err = wtns
+            .calculate_witness(multiplier_inputs(), true)
+            .unwrap_err();
+        assert!(err.to_string().contains("exceeds the limit of 3"));
+
+        wtns.set_limits(Limits {
+            max_input_values: 1,
+            ..Limits::default()
+        });
+        let err = wtns
+            .calculate_witness(multiplier_inputs(), true)
+            .unwrap_err();
+        assert!(err.to_string().contains("limit of 1 input values"));
+    }
+
+    #[test]
+    fn json_witness() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let json = wtns
+            .calculate_witness_json(multiplier_inputs(), true)
+            .unwrap();
+        // exported by snarkjs for the same witness
+        let expected = std::fs::read_to_string("./test-vectors/mycircuit-witness.json").unwrap();
+        assert_eq!(json, expected);
+        assert_eq!(super::super::witness_json(&[]), "[]");
+    }
+
+    #[test]
+    fn prime_without_init() {
+        let bn254 = BigInt::parse_bytes(
+            b"21888242871839275222246405745257275088548364400416034343698204186575808495617",
+            10,
+        )
+        .unwrap();
+        for path in [
+            "./test-vectors/multiplier2.wasm",
+            "./test-vectors/circom2_multiplier2.wasm",
+        ] {
+            let wtns = WitnessCalculator::new(path).unwrap();
+            wtns.reinit(true).unwrap();
+            let initialized = wtns
+                .with_wasm(|instance| {
+                    instance.get_raw_prime()?;
+                    from_array32(read_limbs_be(instance, 8)?)
+                })
+                .unwrap();
+            assert_eq!(&initialized, wtns.prime());
+            assert_eq!(*wtns.prime(), bn254);
+        }
+    }
+
+    #[cfg(feature = "memmap")]
+    #[test]
+    fn mmap_wtns() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let path = std::env::temp_dir().join("ark-circom-mmap-test.wtns");
+        wtns.calculate_witness_mmap(multiplier_inputs(), true, &path)
+            .unwrap();
+
+        let witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        let mut expected = Vec::new();
+        wtns.write_wtns(&witness, &mut expected).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn witness_progress() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let mut reports = Vec::new();
+        let witness = wtns
+            .calculate_witness_with_progress(multiplier_inputs(), true, |done, total| {
+                reports.push((done, total))
+            })
+            .unwrap();
+        assert_eq!(witness.len(), 4);
+        assert_eq!(reports, [(1, 4), (2, 4), (3, 4), (4, 4)]);
+    }
+
+    #[test]
+    fn likely_negative() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(-3)]),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ];
+        let witness = wtns.calculate_witness(inputs, true).unwrap();
+        assert_eq!(
+            wtns.likely_negative_elements(&witness),
+            vec![(1, BigInt::from(-33)), (2, BigInt::from(-3))]
+        );
+
+        let witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert!(wtns.likely_negative_elements(&witness).is_empty());
+
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let small = WitnessCalculator::from_bytes_with_prime(&data, BigInt::from(101)).unwrap();
+        let witness = [0, 1, 50, 51, 100].map(BigInt::from);
+        assert_eq!(
+            small.likely_negative_elements(&witness),
+            vec![(3, BigInt::from(-50)), (4, BigInt::from(-1))]
+        );
+    }
+
+    #[test]
+    fn timeout() {
+        use std::time::Duration;
+        let mut wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let witness = wtns
+            .calculate_witness_with_timeout(multiplier_inputs(), true, Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(
+            witness,
+            wtns.calculate_witness(multiplier_inputs(), true).unwrap()
+        );
+
+        // an unknown signal makes the circuit report an exception, which never returns
+        wtns.register_import(RUNTIME_MODULE, "exceptionHandler", |module, m, n| {
+            module.link_function::<i32, ()>(m, n, blocking_exception_handler_wrap)
+        });
+        let unknown = vec![("c".to_string(), vec![BigInt::from(3)])];
+        let err = wtns
+            .calculate_witness_with_timeout(unknown, true, Duration::from_millis(100))
+            .unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::Timeout(_))
+        ));
+
+        // the calculator's own instance isn't affected by the abandoned calculation
+        assert_eq!(
+            wtns.calculate_witness(multiplier_inputs(), true).unwrap(),
+            witness
+        );
+
+        // no thread is started while the stuck one runs, but clones have their own
+        let err = wtns
+            .calculate_witness_with_timeout(multiplier_inputs(), true, Duration::from_secs(60))
+            .unwrap_err();
+        assert!(err.to_string().contains("still running"));
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::Timeout(_))
+        ));
+        let clone = wtns.clone();
+        assert_eq!(
+            clone
+                .calculate_witness_with_timeout(multiplier_inputs(), true, Duration::from_secs(60))
+                .unwrap(),
+            witness
+        );
+
+        // the thread is reused once the stuck calculation returns
+        UNBLOCK_EXCEPTIONS.store(true, std::sync::atomic::Ordering::SeqCst);
+        let mut tries = 0;
+        let witness_after = loop {
+            match wtns.calculate_witness_with_timeout(
+                multiplier_inputs(),
+                true,
+                Duration::from_secs(60),
+            ) {
+                Err(e) if e.to_string().contains("still running") && tries < 1000 => {
+                    tries += 1;
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                result => break result.unwrap(),
+            }
+        };
+        assert_eq!(witness_after, witness);
+    }
+
+    // Lets the circuits blocked in `blocking_exception_handler` return
+    static UNBLOCK_EXCEPTIONS: std::sync::atomic::AtomicBool =
+        std::sync::atomic::AtomicBool::new(false);
+
+    wasm3::make_func_wrapper!(
+        blocking_exception_handler_wrap: blocking_exception_handler(_code: i32) -> ()
+    );
+    fn blocking_exception_handler(_code: i32) {
+        while !UNBLOCK_EXCEPTIONS.load(std::sync::atomic::Ordering::SeqCst) {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn hashed_input_names() {
+        let hashes = WitnessCalculator::input_hashes(multiplier_inputs());
+        assert_eq!(
+            hashes,
+            vec![
+                ("a".to_string(), 0xaf63dc4c, 0x8601ec8c),
+                ("b".to_string(), 0xaf63df4c, 0x8601f1a5),
+            ]
+        );
+    }
+
+    #[test]
+    fn clones_on_threads() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let wtns = WitnessCalculator::from_bytes(&data).unwrap();
+        let expected = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+
+        let handles = (0..2)
+            .map(|_| {
+                let clone = wtns.clone();
+                assert!(Arc::ptr_eq(&clone.data, &wtns.data));
+                assert_eq!(clone.data(), &data[..]);
+                std::thread::spawn(move || clone.calculate_witness(multiplier_inputs(), true))
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn reduced_witness() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(-3)]),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ];
+        let witness = wtns.calculate_witness_reduced(inputs, true).unwrap();
+        let prime = wtns.prime().magnitude();
+        assert_eq!(witness[1], prime - 33u32);
+        assert_eq!(witness[2], prime - 3u32);
+        assert_eq!(witness[3], BigUint::from(11u32));
+    }
+
+    #[test]
+    fn name_section() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert!(wtns.has_name_section());
+        let wtns = WitnessCalculator::new("./test-vectors/circom2_multiplier2.wasm").unwrap();
+        assert!(!wtns.has_name_section());
+    }
+
+    #[test]
+    fn supplied_prime() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let prime = WitnessCalculator::from_bytes(&data)
+            .unwrap()
+            .prime()
+            .clone();
+
+        let wtns = WitnessCalculator::from_bytes_with_prime(&data, prime.clone()).unwrap();
+        assert_eq!(wtns.n64, 4);
+        assert_eq!(wtns.version().unwrap(), CircomVersion::V2(2));
+        let witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert_eq!(witness[1], BigInt::from(33));
+
+        let wrong = WitnessCalculator::from_bytes_with_prime(&data, prime - 2).unwrap();
+        let err = wrong
+            .calculate_witness(multiplier_inputs(), true)
+            .unwrap_err();
+        assert!(matches!(CircomError::of(&err), Some(CircomError::Load(_))));
+        assert!(err
+            .to_string()
+            .contains("does not match the supplied prime"));
+
+        WitnessCalculator::from_bytes_with_prime(&data, BigInt::from(0)).unwrap_err();
+    }
+
+    #[test]
+    fn zero_prime_of_circuit() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        // a corrupt circuit whose `getRawPrime` leaves zeros in the shared memory
+        let mock = MockCircom2::new(BigInt::from(0), vec![]);
+        let prime = mock.prime().unwrap();
+        assert_eq!(prime, BigInt::from(0));
+
+        let err =
+            WitnessCalculator::with_metadata(&data, prime, None, runtime_imports()).unwrap_err();
+        assert!(matches!(CircomError::of(&err), Some(CircomError::Load(_))));
+        assert_eq!(err.to_string(), "0 is not a valid prime");
+    }
+
+    #[test]
+    fn supplied_field() {
+        let data = std::fs::read("./test-vectors/multiplier2.wasm").unwrap();
+        let prime = WitnessCalculator::from_bytes(&data)
+            .unwrap()
+            .prime()
+            .clone();
+
+        let wtns = WitnessCalculator::from_bytes_with_field(&data, prime.clone(), 8).unwrap();
+        let witness = wtns.calculate_witness(multiplier_inputs(), true).unwrap();
+        assert_eq!(witness[1], BigInt::from(33));
+
+        let wrong = WitnessCalculator::from_bytes_with_field(&data, prime.clone(), 9).unwrap();
+        let err = wrong
+            .calculate_witness(multiplier_inputs(), true)
+            .unwrap_err();
+        assert!(matches!(CircomError::of(&err), Some(CircomError::Load(_))));
+        assert!(err.to_string().contains("do not match the supplied 9"));
+
+        // checked up front where possible
+        WitnessCalculator::from_bytes_with_field(&data, prime.clone(), 7).unwrap_err();
+        WitnessCalculator::from_bytes_with_field(&data, prime, 0).unwrap_err();
+    }
+
+    #[test]
+    fn circom2_version() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        assert_eq!(wtns.version().unwrap(), CircomVersion::V2(2));
+    }
+
+    #[test]
+    fn prime_bytes() {
+        let wtns = WitnessCalculator::new("./test-vectors/multiplier2.wasm").unwrap();
+        let bytes = wtns.prime_le_bytes();
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(
+            BigInt::from_bytes_le(num_bigint::Sign::Plus, &bytes),
+            *wtns.prime()
+        );
+        assert_eq!(
+            wtns.prime().to_string(),
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617"
+        );
+    }
 }
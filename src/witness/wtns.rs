@@ -0,0 +1,217 @@
+//! Witness serialization to snarkjs' `.wtns` and JSON formats
+//!
+//! The file consists of the `wtns` magic, the version (2) and the number of sections (2),
+//! followed by the sections, each prefixed with its type (u32) and byte size (u64):
+//!  Header(1)
+//!       n8 (field element size in bytes)
+//!       prime
+//!       number of witness elements
+//!  Witness(2)
+//!       each element as n8 little-endian bytes
+use super::{CircomError, InputError};
+use byteorder::{LittleEndian, WriteBytesExt};
+use color_eyre::{eyre::eyre, Result};
+use num_bigint::{BigInt, Sign};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Formats the witness as a JSON array of decimal strings, exactly like
+/// `snarkjs wtns export json` does.
+pub fn witness_json(witness: &[BigInt]) -> String {
+    if witness.is_empty() {
+        return "[]".to_string();
+    }
+    let elements = witness
+        .iter()
+        .map(|w| format!(" \"{}\"", w))
+        .collect::<Vec<_>>();
+    format!("[\n{}\n]", elements.join(",\n"))
+}
+
+/// Writes the witness in snarkjs' `.wtns` format, with each element encoded as `n64 * 8`
+/// little-endian bytes. Negative elements are reduced into the field first.
+pub fn write_wtns<W: Write>(
+    witness: &[BigInt],
+    prime: &BigInt,
+    n64: u32,
+    mut writer: W,
+) -> Result<()> {
+    let n8 = n64 * 8;
+    write_wtns_header(&mut writer, prime, n64, witness.len() as u32)?;
+    for w in witness {
+        writer
+            .write_all(&to_le_bytes(w, prime, n8)?)
+            .map_err(CircomError::Io)?;
+    }
+
+    writer.flush().map_err(CircomError::Io)?;
+    Ok(())
+}
+
+// Writes everything up to the witness elements, i.e. the magic, the header section and the
+// start of the witness section
+pub(crate) fn write_wtns_header<W: Write>(
+    writer: &mut W,
+    prime: &BigInt,
+    n64: u32,
+    witness_len: u32,
+) -> Result<()> {
+    let prime_bytes = to_le_bytes(prime, prime, n64 * 8)?;
+    write_header(writer, &prime_bytes, witness_len).map_err(CircomError::Io)?;
+    Ok(())
+}
+
+fn write_header<W: Write>(writer: &mut W, prime: &[u8], witness_len: u32) -> io::Result<()> {
+    let n8 = prime.len() as u32;
+
+    writer.write_all(b"wtns")?;
+    writer.write_u32::<LittleEndian>(2)?;
+    writer.write_u32::<LittleEndian>(2)?;
+
+    writer.write_u32::<LittleEndian>(1)?;
+    writer.write_u64::<LittleEndian>(4 + n8 as u64 + 4)?;
+    writer.write_u32::<LittleEndian>(n8)?;
+    writer.write_all(prime)?;
+    writer.write_u32::<LittleEndian>(witness_len)?;
+
+    writer.write_u32::<LittleEndian>(2)?;
+    writer.write_u64::<LittleEndian>(n8 as u64 * witness_len as u64)
+}
+
+/// Writes the witness to a `.wtns` file at `path`, see [`write_wtns`].
+pub fn write_wtns_file(
+    witness: &[BigInt],
+    prime: &BigInt,
+    n64: u32,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let file = File::create(path).map_err(CircomError::Io)?;
+    write_wtns(witness, prime, n64, BufWriter::new(file))
+}
+
+/// Encodes the witness as one contiguous blob, e.g. to pass it across an FFI boundary: the
+/// number of elements as a little-endian `u32`, followed by every element as `n64 * 8`
+/// little-endian bytes. See [`witness_from_blob`] for the reverse.
+///
+/// Returns an [`InputError::WitnessElement`] error if an element is negative or doesn't fit
+/// in `n64 * 8` bytes, which doesn't happen for witnesses calculated over a prime of `n64`
+/// words.
+pub fn witness_to_blob(witness: &[BigInt], n64: u32) -> Result<Vec<u8>> {
+    let n8 = n64 as usize * 8;
+    let mut blob = Vec::with_capacity(4 + witness.len() * n8);
+    blob.extend_from_slice(&(witness.len() as u32).to_le_bytes());
+    for (index, w) in witness.iter().enumerate() {
+        let (sign, bytes) = w.to_bytes_le();
+        if sign == Sign::Minus || bytes.len() > n8 {
+            return Err(CircomError::Input(InputError::WitnessElement {
+                index,
+                value: w.to_string(),
+                n8,
+            })
+            .into());
+        }
+        let start = blob.len();
+        blob.extend_from_slice(&bytes);
+        blob.resize(start + n8, 0);
+    }
+    Ok(blob)
+}
+
+/// Decodes a witness encoded with [`witness_to_blob`].
+pub fn witness_from_blob(blob: &[u8], n64: u32) -> Result<Vec<BigInt>> {
+    let n8 = n64 as usize * 8;
+    if blob.len() < 4 || n8 == 0 {
+        return Err(eyre!(
+            "A witness blob of {} bytes has no header",
+            blob.len()
+        ));
+    }
+    let (count, elements) = blob.split_at(4);
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+    if elements.len() as u64 != count as u64 * n8 as u64 {
+        return Err(eyre!(
+            "A witness blob of {} elements of {} bytes can't hold {} bytes",
+            count,
+            n8,
+            elements.len()
+        ));
+    }
+    Ok(elements
+        .chunks_exact(n8)
+        .map(|element| BigInt::from_bytes_le(Sign::Plus, element))
+        .collect())
+}
+
+// Encodes `value` as exactly `n8` little-endian bytes
+fn to_le_bytes(value: &BigInt, prime: &BigInt, n8: u32) -> Result<Vec<u8>> {
+    let value = if value.sign() == Sign::Minus {
+        value % prime + prime
+    } else {
+        value.clone()
+    };
+    let (_, mut bytes) = value.to_bytes_le();
+    if bytes.len() > n8 as usize {
+        return Err(eyre!("{} does not fit in {} bytes", value, n8));
+    }
+    bytes.resize(n8 as usize, 0);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout() {
+        let prime = BigInt::from(0xffffffffu64);
+        let witness = vec![BigInt::from(1), BigInt::from(0x0102), BigInt::from(-1)];
+        let mut buf = Vec::new();
+        write_wtns(&witness, &prime, 1, &mut buf).unwrap();
+
+        let mut expected = b"wtns".to_vec();
+        expected.extend_from_slice(&[2, 0, 0, 0, 2, 0, 0, 0]);
+        // header section
+        expected.extend_from_slice(&[1, 0, 0, 0, 16, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[8, 0, 0, 0]);
+        expected.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[3, 0, 0, 0]);
+        // witness section
+        expected.extend_from_slice(&[2, 0, 0, 0, 24, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[2, 1, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0xfe, 0xff, 0xff, 0xff, 0, 0, 0, 0]);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn blob_round_trip() {
+        let witness = vec![
+            BigInt::from(1),
+            BigInt::from(0x0102),
+            BigInt::from(u64::MAX),
+        ];
+        let blob = witness_to_blob(&witness, 1).unwrap();
+        assert_eq!(blob.len(), 4 + 3 * 8);
+        assert_eq!(&blob[..12], &[3, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(witness_from_blob(&blob, 1).unwrap(), witness);
+
+        assert!(witness_from_blob(&blob[..blob.len() - 1], 1).is_err());
+        assert!(witness_from_blob(&blob[..2], 1).is_err());
+        assert!(witness_from_blob(&witness_to_blob(&[], 4).unwrap(), 4)
+            .unwrap()
+            .is_empty());
+
+        let err = witness_to_blob(&[BigInt::from(1), BigInt::from(-1)], 1).unwrap_err();
+        assert!(matches!(
+            CircomError::of(&err),
+            Some(CircomError::Input(InputError::WitnessElement {
+                index: 1,
+                ..
+            }))
+        ));
+        assert!(witness_to_blob(&[BigInt::from(1) << 64], 1).is_err());
+    }
+}
@@ -0,0 +1,26 @@
+#!/usr/bin/env node
+// Adds a case to the conformance vectors, replacing the case of the same circuit and inputs
+// if there is one, so that rerunning build.sh updates the vectors instead of duplicating them:
+// append-case.js <wasm path from the crate root> <input.json> <witness.json> <conformance.json>
+const fs = require("fs");
+
+const [wasm, inputPath, witnessPath, conformancePath] = process.argv.slice(2);
+
+// circom flattens array inputs in row-major order
+const flatten = (value) => (Array.isArray(value) ? value.flatMap(flatten) : [String(value)]);
+
+const input = JSON.parse(fs.readFileSync(inputPath, "utf8"));
+const inputs = Object.fromEntries(
+    Object.entries(input).map(([name, value]) => [name, flatten(value)])
+);
+const witness = JSON.parse(fs.readFileSync(witnessPath, "utf8")).map(String);
+
+const cases = JSON.parse(fs.readFileSync(conformancePath, "utf8"));
+const key = (c) => JSON.stringify([c.wasm, c.inputs]);
+const index = cases.findIndex((c) => key(c) === key({ wasm, inputs }));
+if (index === -1) {
+    cases.push({ wasm, inputs, witness });
+} else {
+    cases[index] = { wasm, inputs, witness };
+}
+fs.writeFileSync(conformancePath, JSON.stringify(cases, null, 2) + "\n");
@@ -0,0 +1,22 @@
+# Compiles the conformance circuits and records the witnesses snarkjs calculates for their
+# inputs in ../conformance.json, which tests/conformance.rs checks the crate against.
+# Needs circom 2, snarkjs and circomlib (`npm install circomlib` in this directory).
+set -e
+
+# every circuit along with the prime it's compiled for
+for entry in poseidon:bn128 lessthan:bn128 multiplier_goldilocks:goldilocks; do
+    circuit=${entry%%:*}
+    prime=${entry#*:}
+    echo "compiling $circuit over $prime"
+    circom $circuit.circom --wasm --prime $prime -l node_modules
+    cp ${circuit}_js/$circuit.wasm ../$circuit.wasm
+
+    for input in $circuit-input*.json; do
+        echo "wtns $input"
+        snarkjs wtns calculate ../$circuit.wasm $input witness.wtns
+        snarkjs wtns export json witness.wtns witness.json
+        node append-case.js ./test-vectors/$circuit.wasm $input witness.json ../conformance.json
+    done
+done
+
+rm -rf *_js witness.wtns witness.json
@@ -0,0 +1,5 @@
+pragma circom 2.0.0;
+
+include "circomlib/circuits/comparators.circom";
+
+component main = LessThan(64);
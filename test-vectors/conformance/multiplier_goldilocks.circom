@@ -0,0 +1,11 @@
+pragma circom 2.0.0;
+
+// multiplier2 compiled with `--prime goldilocks`, whose field elements take two 32-bit words
+template Multiplier2() {
+    signal input a;
+    signal input b;
+    signal output c;
+    c <== a * b;
+}
+
+component main = Multiplier2();
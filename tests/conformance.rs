@@ -0,0 +1,43 @@
+//! Checks the witnesses produced by the wasm3 backend against golden vectors generated by
+//! running the same circuits through circom's reference JS witness calculator.
+//!
+//! `test-vectors/conformance/build.sh` compiles the circomlib Poseidon and LessThan circuits,
+//! whose witnesses cover field reduction and multi-limb values, and a multiplier over the
+//! Goldilocks field, whose elements take two words, and records the witnesses snarkjs
+//! calculates for them in the vectors.
+use ark_circom::WitnessCalculator;
+use color_eyre::Result;
+use num_bigint::BigInt;
+use serde_json::Value;
+
+fn to_bigints(values: &Value) -> Vec<BigInt> {
+    values
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_str().unwrap().parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn witnesses_match_golden_vectors() -> Result<()> {
+    let golden: Value =
+        serde_json::from_str(&std::fs::read_to_string("./test-vectors/conformance.json")?)?;
+
+    for case in golden.as_array().unwrap() {
+        let wasm = case["wasm"].as_str().unwrap();
+        let inputs = case["inputs"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(name, values)| (name.clone(), to_bigints(values)))
+            .collect::<Vec<_>>();
+        let expected = to_bigints(&case["witness"]);
+
+        let wtns = WitnessCalculator::new(wasm)?;
+        let witness = wtns.calculate_witness(inputs.clone(), true)?;
+        assert_eq!(witness, expected, "{} with inputs {:?}", wasm, inputs);
+    }
+
+    Ok(())
+}
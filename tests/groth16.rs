@@ -105,3 +105,43 @@ fn witness_generation_circom2() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(feature = "groth16")]
+#[test]
+fn groth16_assignment() -> Result<()> {
+    let cfg = CircomConfig::<Bn254>::new(
+        "./test-vectors/multiplier2.wasm",
+        "./test-vectors/multiplier2.r1cs",
+    )?;
+    let wtns = cfg.wtns.clone();
+    let inputs = vec![
+        ("a".to_string(), vec![num_bigint::BigInt::from(3)]),
+        ("b".to_string(), vec![num_bigint::BigInt::from(11)]),
+    ];
+    let assignment = wtns.calculate_groth16_assignment(inputs, false, &cfg.r1cs)?;
+    assert_eq!(assignment.instance.len(), cfg.r1cs.num_inputs);
+    assert_eq!(assignment.witness.len(), cfg.r1cs.num_aux);
+
+    let mut builder = CircomBuilder::new(cfg);
+    builder.push_input("a", 3);
+    builder.push_input("b", 11);
+    let circom = builder.setup();
+
+    let mut rng = thread_rng();
+    let params = GrothBn::generate_random_parameters_with_reduction(circom, &mut rng)?;
+    let circom = builder.build()?;
+    assert_eq!(
+        assignment.public_inputs(),
+        &circom.get_public_inputs().unwrap()[..]
+    );
+
+    let proof = GrothBn::prove(&params, circom, &mut rng)?;
+    let pvk = GrothBn::process_vk(&params.vk).unwrap();
+    assert!(GrothBn::verify_with_processed_vk(
+        &pvk,
+        assignment.public_inputs(),
+        &proof
+    )?);
+
+    Ok(())
+}